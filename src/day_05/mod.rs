@@ -5,13 +5,10 @@ use nom::multi::separated_list1;
 use nom::sequence::separated_pair;
 use nom::IResult;
 
+use crate::vector::Vector;
 use crate::*;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Coordinate {
-    pub x: u32,
-    pub y: u32,
-}
+pub type Coordinate = Vector<u32, 2>;
 
 #[derive(PartialEq, Eq)]
 pub enum VentsClass {
@@ -28,34 +25,27 @@ pub struct Vents {
 
 impl Vents {
     pub fn class(&self) -> VentsClass {
-        if self.start.x == self.end.x {
+        if self.start[0] == self.end[0] {
             VentsClass::Vertical
-        } else if self.start.y == self.end.y {
+        } else if self.start[1] == self.end[1] {
             VentsClass::Horizontal
         } else {
             VentsClass::Diagonal
         }
     }
 
+    /// Lists the points from `start` to `end`.  Diagonal vents are
+    /// always at 45 degrees, so every line moves by at most one along
+    /// each axis per point.
     pub fn covered(&self) -> impl Iterator<Item = Coordinate> {
-        let ret: Box<dyn Iterator<Item = Coordinate>> = match self.class() {
-            VentsClass::Vertical => Box::new(
-                std::iter::repeat(self.start.x)
-                    .zip(riar(self.start.y..=self.end.y))
-                    .map(|(x, y)| Coordinate { x, y }),
-            ),
-            VentsClass::Horizontal => Box::new(
-                riar(self.start.x..=self.end.x)
-                    .zip(std::iter::repeat(self.start.y))
-                    .map(|(x, y)| Coordinate { x, y }),
-            ),
-            VentsClass::Diagonal => Box::new(
-                riar(self.start.x..=self.end.x)
-                    .zip(riar(self.start.y..=self.end.y))
-                    .map(|(x, y)| Coordinate { x, y }),
-            ),
-        };
-        ret
+        let start = self.start.map(|&t| i64::from(t));
+        let delta = self.end.map(|&t| i64::from(t)) - start;
+        let step = delta.map(|d| d.signum());
+        (0..=delta.norm_max()).map(move |k| {
+            // This can't fail: every point lies between `start` and
+            // `end`, which are both `u32`s.
+            (start + step * k).map(|&t| u32::try_from(t).unwrap_or_else(|_| unreachable!()))
+        })
     }
 }
 
@@ -67,7 +57,7 @@ pub fn parse_vents<'a>() -> impl FnMut(&'a str) -> IResult<&'a str, Vec<Vents>>
                 char(','),
                 parse_integral_nonnegative(),
             ),
-            |(x, y)| Coordinate { x, y },
+            |(x, y)| Vector::from([x, y]),
         )
     }
 
@@ -1,6 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
+use counter::Counter;
 use itertools::Itertools;
+use ndarray::Array2;
 use nom::bytes::complete::tag;
 use nom::character::complete::{alpha1, newline, satisfy};
 use nom::combinator::{all_consuming, map};
@@ -43,3 +45,248 @@ pub fn parse_input<'a>() -> impl FnMut(&'a str) -> IResult<&'a str, Input> {
         |(template, rules)| Input { template, rules },
     )
 }
+
+/// A pair of adjacent elements that can occur in the polymer, but for
+/// which there is no insertion rule.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MissingRule(pub char, pub char);
+
+/// Element counts for a polymer.
+pub type Histogram = Counter<char, u128>;
+
+/// Runs pair insertion without ever building the polymer itself.
+///
+/// Only the number of occurrences of each pair is tracked.  Every
+/// pair `ab` with a rule `ab -> x` turns into the pairs `ax` and `xb`,
+/// so a step is a linear map on pair counts, and `n` steps can be
+/// computed by exponentiation by squaring.
+pub struct Polymerizer {
+    /// Every pair that can occur in the polymer.
+    pairs: Vec<(char, char)>,
+    /// The two pairs (as indices into `pairs`) that each pair turns
+    /// into after a single step.
+    children: Vec<(usize, usize)>,
+    /// Pair counts for the template.
+    init: Vec<u128>,
+    /// The last element of the template.  Insertion never moves it.
+    last: char,
+}
+
+impl TryFrom<Input> for Polymerizer {
+    type Error = MissingRule;
+
+    /// Checks that every pair that can occur in the polymer has a
+    /// rule.
+    fn try_from(input: Input) -> Result<Self, Self::Error> {
+        let mut indices = HashMap::new();
+        let mut pairs = Vec::new();
+        let mut queue = VecDeque::new();
+        let mut visit = |p: (char, char), queue: &mut VecDeque<_>| {
+            *indices.entry(p).or_insert_with(|| {
+                pairs.push(p);
+                queue.push_back(p);
+                pairs.len() - 1
+            })
+        };
+
+        let template = input.template.iter().copied().tuple_windows().collect_vec();
+        for &p in template.iter() {
+            visit(p, &mut queue);
+        }
+        let mut children = Vec::new();
+        while let Some(p @ (a, b)) = queue.pop_front() {
+            let &x = input.rules.get(&p).ok_or(MissingRule(a, b))?;
+            children.push((visit((a, x), &mut queue), visit((x, b), &mut queue)));
+        }
+
+        let mut init = vec![0; pairs.len()];
+        for p in template {
+            init[indices[&p]] += 1;
+        }
+
+        let last = *input
+            .template
+            .last()
+            .expect("the template should not be empty");
+        Ok(Self {
+            pairs,
+            children,
+            init,
+            last,
+        })
+    }
+}
+
+impl Polymerizer {
+    /// Returns the element counts after `n` steps, or `None` if they
+    /// do not fit in a `u128`.
+    ///
+    /// This takes O(log n) steps, so `n` can be very large (as long
+    /// as the counts still fit).
+    pub fn histogram_after(&self, n: u64) -> Option<Histogram> {
+        let mut counts = self.init.clone();
+        let mut power = self.transition();
+        // `power` covers `stride` steps, and `done` steps have been
+        // applied to `counts` so far.
+        let mut stride = 1_u64;
+        let mut done = 0;
+        while done < n {
+            if (n >> stride.trailing_zeros()) & 1 == 1 {
+                match Self::apply(&counts, &power) {
+                    Some(next) => counts = next,
+                    None => break,
+                }
+                done += stride;
+            }
+            if done < n {
+                match Self::compose(&power, &power) {
+                    Some(next) => power = next,
+                    None => break,
+                }
+                stride *= 2;
+            }
+        }
+        // An entry of `power` can overflow even though the counts it
+        // would be applied to still fit, e.g. for a pair that only
+        // shows up after a few steps.  Finish one step at a time: the
+        // number of pairs doubles with every step, so this gives up
+        // after at most 128 more steps.
+        let counts = (done..n).try_fold(counts, |counts, _| self.step(&counts))?;
+        self.histogram(&counts)
+    }
+
+    /// Returns the element counts after each step, starting with the
+    /// template itself.
+    ///
+    /// The iterator ends when the counts no longer fit in a `u128`.
+    pub fn histograms(&self) -> impl Iterator<Item = Histogram> + '_ {
+        std::iter::successors(Some(self.init.clone()), |counts| self.step(counts))
+            .map_while(|counts| self.histogram(&counts))
+    }
+
+    fn step(&self, counts: &[u128]) -> Option<Vec<u128>> {
+        let mut ret = vec![0_u128; counts.len()];
+        for (&n, &(l, r)) in counts.iter().zip(self.children.iter()) {
+            ret[l] = ret[l].checked_add(n)?;
+            ret[r] = ret[r].checked_add(n)?;
+        }
+        Some(ret)
+    }
+
+    fn histogram(&self, counts: &[u128]) -> Option<Histogram> {
+        // Every element except the last is the first element of
+        // exactly one pair.
+        let mut ret = Histogram::new();
+        ret[&self.last] = 1;
+        for (&(a, _), &n) in self.pairs.iter().zip(counts.iter()) {
+            ret[&a] = ret[&a].checked_add(n)?;
+        }
+        ret.retain(|_, n| *n > 0);
+        Some(ret)
+    }
+
+    /// The matrix of a single step: the entry at `(i, j)` is the
+    /// number of copies of pair `j` that one copy of pair `i` turns
+    /// into.
+    fn transition(&self) -> Array2<u128> {
+        let n = self.pairs.len();
+        let mut ret = Array2::zeros((n, n));
+        for (i, &(l, r)) in self.children.iter().enumerate() {
+            ret[(i, l)] += 1;
+            ret[(i, r)] += 1;
+        }
+        ret
+    }
+
+    fn apply(counts: &[u128], m: &Array2<u128>) -> Option<Vec<u128>> {
+        let mut ret = vec![0_u128; counts.len()];
+        for ((i, j), &k) in m.indexed_iter() {
+            ret[j] = ret[j].checked_add(counts[i].checked_mul(k)?)?;
+        }
+        Some(ret)
+    }
+
+    fn compose(m1: &Array2<u128>, m2: &Array2<u128>) -> Option<Array2<u128>> {
+        let mut ret = Array2::<u128>::zeros(m1.dim());
+        for ((i, j), &k) in m1.indexed_iter() {
+            if k == 0 {
+                continue;
+            }
+            for (l, &k2) in m2.row(j).indexed_iter() {
+                ret[(i, l)] = ret[(i, l)].checked_add(k.checked_mul(k2)?)?;
+            }
+        }
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod test_polymerizer {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+NNCB
+
+CH -> B
+HH -> N
+CB -> H
+NH -> C
+HB -> C
+HC -> B
+HN -> C
+NN -> C
+BH -> H
+NC -> B
+NB -> B
+BN -> B
+BB -> N
+BC -> B
+CC -> N
+CN -> C
+";
+
+    fn example() -> Polymerizer {
+        let (_, input) = parse_input()(EXAMPLE).unwrap();
+        Polymerizer::try_from(input).unwrap()
+    }
+
+    #[test]
+    fn test_histogram_after_example() {
+        let histogram = example().histogram_after(10).unwrap();
+        assert_eq!(histogram[&'B'], 1749);
+        assert_eq!(histogram[&'C'], 298);
+        assert_eq!(histogram[&'H'], 161);
+        assert_eq!(histogram[&'N'], 865);
+    }
+
+    #[test]
+    fn test_histogram_after_matches_histograms() {
+        let polymerizer = example();
+        for (n, histogram) in polymerizer.histograms().take(50).enumerate() {
+            assert_eq!(polymerizer.histogram_after(n as u64).unwrap(), histogram);
+        }
+    }
+
+    #[test]
+    fn test_histogram_after_large_power() {
+        // `BB` doubles every step, so squaring the transition matrix up
+        // to 128 steps overflows, but the counts after 128 steps (with
+        // `BB` only showing up after the first) still fit.
+        let (_, input) = parse_input()("AC\n\nAC -> B\nAB -> B\nBB -> B\nBC -> B\n").unwrap();
+        let polymerizer = Polymerizer::try_from(input).unwrap();
+        let histogram = polymerizer.histogram_after(128).unwrap();
+        assert_eq!(histogram[&'B'], u128::MAX);
+        assert_eq!(polymerizer.histograms().nth(128), Some(histogram));
+        assert_eq!(polymerizer.histogram_after(129), None);
+    }
+
+    #[test]
+    fn test_missing_rule() {
+        let (_, mut input) = parse_input()(EXAMPLE).unwrap();
+        input.rules.remove(&('C', 'N'));
+        assert_eq!(
+            Polymerizer::try_from(input).err(),
+            Some(MissingRule('C', 'N'))
+        );
+    }
+}
@@ -1,12 +1,10 @@
-use std::collections::BinaryHeap;
-
 use ndarray::Array2;
 use nom::character::complete::{digit1, newline};
 use nom::combinator::{all_consuming, map_res};
 use nom::multi::separated_list1;
 use nom::{IResult, InputIter};
 
-use crate::{newline_terminated, Array2Ext};
+use crate::{grid, newline_terminated, Array2Ext};
 
 pub fn parse_input<'a>() -> impl FnMut(&'a str) -> IResult<&'a str, Array2<u32>> {
     map_res(
@@ -22,49 +20,108 @@ pub fn parse_input<'a>() -> impl FnMut(&'a str) -> IResult<&'a str, Array2<u32>>
     )
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
-struct Path {
-    ix: (usize, usize),
-    risk: u32,
+/// A risk map made of copies of a base map, tiled `factor` times in
+/// each direction.
+///
+/// Each tile's risks are one higher than the tile above it or to its
+/// left, wrapping around from 9 back to 1.  Risks are computed on
+/// demand, so the full map is never allocated.
+pub struct TiledGrid<'a> {
+    base: &'a Array2<u32>,
+    factor: usize,
 }
 
-impl PartialOrd for Path {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl<'a> TiledGrid<'a> {
+    pub fn new(base: &'a Array2<u32>, factor: usize) -> Self {
+        Self { base, factor }
     }
-}
 
-impl Ord for Path {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // BinaryHeap is a max-heap, and we always want to find the
-        // least expensive path, so we reverse the ordering.
-        other.risk.cmp(&self.risk)
+    pub fn shape2(&self) -> (usize, usize) {
+        let (h, w) = self.base.shape2();
+        (h * self.factor, w * self.factor)
     }
-}
 
-pub fn dijkstra(input: Array2<u32>) -> u32 {
-    let (h, w) = input.shape2();
-    let target = (h - 1, w - 1);
-    let mut heap = BinaryHeap::new();
-    heap.push(Path {
-        ix: (0, 0),
-        risk: 0,
-    });
-    let mut risks = Array2::from_elem(input.shape2(), u32::MAX);
-    loop {
-        let next = heap.pop().unwrap();
-        if next.ix == target {
-            break next.risk;
-        }
-        if next.risk > risks[next.ix] {
-            continue;
-        }
-        for ix in input.cardinal_neighbor_indices(next.ix).into_iter() {
-            let risk = next.risk + input[ix];
-            if risk < risks[ix] {
-                heap.push(Path { ix, risk });
-                risks[ix] = risk;
-            }
+    /// Returns the risk at the given index, which must be in bounds.
+    pub fn get(&self, (i, j): (usize, usize)) -> u32 {
+        let (h, w) = self.base.shape2();
+        let (ti, i) = (i / h, i % h);
+        let (tj, j) = (j / w, j % w);
+        let bump = ((ti + tj) % 9) as u32;
+        let risk = self.base[(i, j)] + bump;
+        if risk > 9 {
+            risk - 9
+        } else {
+            risk
         }
     }
+
+    pub fn cardinal_neighbor_indices(&self, ix: (usize, usize)) -> Vec<(usize, usize)> {
+        grid::cardinal_neighbors(self.shape2(), ix)
+    }
+}
+
+/// Returns the lowest total risk of a path from the top left to the
+/// bottom right, or `None` if the map is empty.
+pub fn lowest_total_risk(risks: &TiledGrid) -> Option<u32> {
+    let (h, w) = risks.shape2();
+    let goal = (h.checked_sub(1)?, w.checked_sub(1)?);
+    // The Manhattan distance only underestimates the remaining risk if
+    // every cell costs at least 1.  Only the first tile can contain
+    // zeros, since later tiles add at least 1 and wrap around to 1.
+    let has_zero = risks.base.iter().any(|&risk| risk == 0);
+    let goals = [goal];
+    let manhattan = grid::manhattan_to(&goals);
+    let (risk, _) = grid::shortest_path(
+        [(0, 0)],
+        goals,
+        |ix| risks.cardinal_neighbor_indices(ix),
+        |ix| risks.get(ix),
+        |ix| if has_zero { 0 } else { manhattan(ix) },
+    )?;
+    Some(risk)
+}
+
+#[cfg(test)]
+mod test_tiled_grid {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn test_tiled_grid_non_square() {
+        let base = array![[8, 9]];
+        let tiled = TiledGrid::new(&base, 3);
+        assert_eq!(tiled.shape2(), (3, 6));
+        let rows = (0..3)
+            .map(|i| (0..6).map(|j| tiled.get((i, j))).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                vec![8, 9, 9, 1, 1, 2],
+                vec![9, 1, 1, 2, 2, 3],
+                vec![1, 2, 2, 3, 3, 4],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tiled_grid_zero_risk() {
+        let base = array![[0, 9]];
+        let tiled = TiledGrid::new(&base, 2);
+        let rows = (0..2)
+            .map(|i| (0..4).map(|j| tiled.get((i, j))).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![vec![0, 9, 1, 1], vec![1, 1, 2, 2]]);
+    }
+
+    #[test]
+    fn test_lowest_total_risk_zero_risk() {
+        // With zero-risk cells the Manhattan distance overestimates,
+        // and A* would settle on the 1 in the middle.
+        let base = array![[0, 0, 0, 0], [0, 0, 1, 0]];
+        assert_eq!(lowest_total_risk(&TiledGrid::new(&base, 1)), Some(0));
+        let base = array![[0, 8, 0, 0], [1, 5, 3, 0]];
+        assert_eq!(lowest_total_risk(&TiledGrid::new(&base, 1)), Some(8));
+    }
 }
@@ -3,7 +3,8 @@ use aoc2021::*;
 use ndarray::Array2;
 
 fn run(input: Array2<u32>) -> u32 {
-    dijkstra(input)
+    lowest_total_risk(&TiledGrid::new(&input, 1)).unwrap()
 }
 
 make_main! {15, parse_input, run}
+make_test! {15, 1, parse_input, run, 508}
@@ -1,75 +1,22 @@
-use combine::error::UnexpectedParse;
-use combine::parser::char::{hex_digit, newline};
-use combine::parser::function::env_parser;
-use combine::parser::range::take;
-use combine::{
-    any, count_min_max, eof, many, one_of, parser, unexpected_any, value, Parser, StdParseResult,
-    Stream,
-};
 use itertools::Itertools;
+use num_bigint::BigUint;
+use num_traits::{CheckedAdd, CheckedMul, One, Zero};
 use ArbOp::*;
 use BinOp::*;
 use Binary::*;
 use Payload::*;
 
-use crate::{FoldMap, FoldMapOption};
+use self::stream::{DecodeError, Decoder};
+
+mod sexpr;
+pub mod stream;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-enum Binary {
+pub enum Binary {
     Zero,
     One,
 }
 
-impl Binary {
-    fn try_to_u8(bits: &[Binary]) -> Option<u8> {
-        if bits.len() >= u8::BITS as usize {
-            return None;
-        }
-        Some(
-            bits.iter()
-                .copied()
-                .enumerate()
-                .fold_map(|(i, b)| {
-                    let j = bits.len() - i - 1;
-                    frunk::semigroup::Any((u8::from(b) << j) & (1 << j))
-                })
-                .0,
-        )
-    }
-
-    fn try_to_u64(bits: &[Binary]) -> Option<u64> {
-        if bits.len() >= u64::BITS as usize {
-            return None;
-        }
-        Some(
-            bits.iter()
-                .copied()
-                .enumerate()
-                .fold_map(|(i, b)| {
-                    let j = bits.len() - i - 1;
-                    frunk::semigroup::Any((u64::from(b) << j) & (1 << j))
-                })
-                .0,
-        )
-    }
-
-    fn try_to_usize(bits: &[Binary]) -> Option<usize> {
-        if bits.len() >= usize::BITS as usize {
-            return None;
-        }
-        Some(
-            bits.iter()
-                .copied()
-                .enumerate()
-                .fold_map(|(i, b)| {
-                    let j = bits.len() - i - 1;
-                    frunk::semigroup::Any((usize::from(b) << j) & (1 << j))
-                })
-                .0,
-        )
-    }
-}
-
 impl From<Binary> for u8 {
     fn from(b: Binary) -> Self {
         match b {
@@ -79,7 +26,7 @@ impl From<Binary> for u8 {
     }
 }
 
-impl From<Binary> for u64 {
+impl From<Binary> for u32 {
     fn from(b: Binary) -> Self {
         match b {
             Zero => 0,
@@ -109,25 +56,7 @@ impl TryFrom<u8> for Binary {
     }
 }
 
-fn unhex<Input>() -> impl Parser<Input, Output = [Binary; 4]>
-where
-    Input: Stream<Token = char>,
-{
-    hex_digit().map(|c| {
-        // This can't fail: the input character has already passed
-        // through the hex_digit parser.
-        let n = u8::from_str_radix(&c.to_string(), 16).unwrap_or_else(|_| unreachable!());
-        let mut ret = [Zero; 4];
-        for (i, b) in ret.iter_mut().enumerate() {
-            // This can't fail: AND-ing with 1 will always produce
-            // either a 0 or a 1.
-            *b = Binary::try_from((n >> (3 - i)) & 1).unwrap_or_else(|_| unreachable!());
-        }
-        ret
-    })
-}
-
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Packet {
     pub version: u8,
     pub payload: Payload,
@@ -138,14 +67,143 @@ impl Packet {
         std::iter::once(self).chain(self.payload.packets())
     }
 
-    pub fn eval(&self) -> u64 {
+    /// Evaluates the expression represented by this packet, using
+    /// checked arithmetic in `T`.
+    pub fn eval<T: Value>(&self) -> Result<T, EvalError> {
         self.payload.eval()
     }
+
+    /// Encodes this packet as a bit stream, using `LengthType::Count`
+    /// for operator packets whenever possible.
+    pub fn to_bits(&self) -> Result<Vec<Binary>, EncodeError> {
+        self.to_bits_with(LengthType::Count)
+    }
+
+    /// Encodes this packet as a bit stream, using `prefer` for
+    /// operator packets whenever possible, and the other length type
+    /// otherwise.
+    pub fn to_bits_with(&self, prefer: LengthType) -> Result<Vec<Binary>, EncodeError> {
+        let mut bits = Vec::new();
+        self.write_bits(prefer, &mut bits)?;
+        Ok(bits)
+    }
+
+    /// Encodes this packet as a hexadecimal transmission, padding the
+    /// bit stream with zeros to a whole number of bytes.
+    pub fn to_hex(&self) -> Result<String, EncodeError> {
+        Ok(bits_to_hex(&self.to_bits()?))
+    }
+
+    fn write_bits(&self, prefer: LengthType, bits: &mut Vec<Binary>) -> Result<(), EncodeError> {
+        if self.version >= 8 {
+            return Err(EncodeError::VersionTooLarge(self.version));
+        }
+        push_bits(bits, self.version.into(), 3);
+        match &self.payload {
+            Literal(n) => {
+                push_bits(bits, 4, 3);
+                let groups = n.to_radix_be(16);
+                for (i, &group) in groups.iter().enumerate() {
+                    bits.push(if i + 1 == groups.len() { Zero } else { One });
+                    push_bits(bits, group.into(), 4);
+                }
+            }
+            Arb(op, args) => {
+                push_bits(bits, u8::from(*op).into(), 3);
+                write_operands(args.iter(), prefer, bits)?;
+            }
+            Bin(op, arg1, arg2) => {
+                push_bits(bits, u8::from(*op).into(), 3);
+                write_operands([arg1.as_ref(), arg2.as_ref()].into_iter(), prefer, bits)?;
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Clone, Debug)]
+/// How an operator packet records the extent of its subpackets.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LengthType {
+    /// Length type ID 0: the total length of the subpackets in bits,
+    /// as a 15-bit number.
+    Bits,
+    /// Length type ID 1: the number of subpackets, as an 11-bit
+    /// number.
+    Count,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EncodeError {
+    /// Versions only get three bits.
+    VersionTooLarge(u8),
+    /// An operator's subpackets are too long and too many to be
+    /// described by either length type.
+    TooManySubpackets,
+}
+
+fn push_bits(bits: &mut Vec<Binary>, n: u64, width: u32) {
+    for i in (0..width).rev() {
+        bits.push(if (n >> i) & 1 == 1 { One } else { Zero });
+    }
+}
+
+fn write_operands<'a, I>(
+    args: I,
+    prefer: LengthType,
+    bits: &mut Vec<Binary>,
+) -> Result<(), EncodeError>
+where
+    I: Iterator<Item = &'a Packet>,
+{
+    let mut body = Vec::new();
+    let mut count = 0;
+    for arg in args {
+        arg.write_bits(prefer, &mut body)?;
+        count += 1;
+    }
+    let fits = |length_type| match length_type {
+        LengthType::Bits => body.len() < 1 << 15,
+        LengthType::Count => count < 1 << 11,
+    };
+    let other = match prefer {
+        LengthType::Bits => LengthType::Count,
+        LengthType::Count => LengthType::Bits,
+    };
+    match [prefer, other].into_iter().find(|&t| fits(t)) {
+        Some(LengthType::Bits) => {
+            bits.push(Zero);
+            push_bits(bits, body.len() as u64, 15);
+        }
+        Some(LengthType::Count) => {
+            bits.push(One);
+            push_bits(bits, count, 11);
+        }
+        None => return Err(EncodeError::TooManySubpackets),
+    }
+    bits.extend(body);
+    Ok(())
+}
+
+fn bits_to_hex(bits: &[Binary]) -> String {
+    let padding = (8 - bits.len() % 8) % 8;
+    bits.iter()
+        .copied()
+        .chain(std::iter::repeat(Zero).take(padding))
+        .collect_vec()
+        .chunks(4)
+        .map(|chunk| {
+            let n = chunk.iter().fold(0, |n, &b| (n << 1) | u32::from(b));
+            // This can't fail: n is made of four bits.
+            char::from_digit(n, 16)
+                .unwrap_or_else(|| unreachable!())
+                .to_ascii_uppercase()
+        })
+        .collect()
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Payload {
-    Literal(u64),
+    Literal(BigUint),
     Arb(ArbOp, Vec<Packet>),
     Bin(BinOp, Box<Packet>, Box<Packet>),
 }
@@ -160,16 +218,53 @@ impl Payload {
         ret
     }
 
-    pub fn eval(&self) -> u64 {
+    pub fn eval<T: Value>(&self) -> Result<T, EvalError> {
         match self {
-            Literal(n) => *n,
-            Arb(op, args) => op.eval(args.iter().map(Packet::eval)),
-            Bin(op, arg1, arg2) => op.eval(arg1.eval(), arg2.eval()),
+            Literal(n) => T::from_literal(n).ok_or(EvalError::Overflow),
+            Arb(op, args) => op.eval(
+                args.iter()
+                    .map(Packet::eval)
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Bin(op, arg1, arg2) => Ok(op.eval(&arg1.eval()?, &arg2.eval()?)),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// A numeric type that packets can be evaluated in.
+pub trait Value: Clone + Ord + Zero + One + CheckedAdd + CheckedMul {
+    /// Converts a literal, returning `None` if it is out of range.
+    fn from_literal(n: &BigUint) -> Option<Self>;
+}
+
+impl Value for u64 {
+    fn from_literal(n: &BigUint) -> Option<Self> {
+        n.try_into().ok()
+    }
+}
+
+impl Value for u128 {
+    fn from_literal(n: &BigUint) -> Option<Self> {
+        n.try_into().ok()
+    }
+}
+
+impl Value for BigUint {
+    fn from_literal(n: &BigUint) -> Option<Self> {
+        Some(n.clone())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EvalError {
+    /// A literal or an intermediate result does not fit in the
+    /// numeric type.
+    Overflow,
+    /// A `Min` or `Max` operator has no arguments.
+    NoArguments(ArbOp),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ArbOp {
     Sum,
     Prod,
@@ -178,15 +273,29 @@ pub enum ArbOp {
 }
 
 impl ArbOp {
-    pub fn eval<I>(&self, args: I) -> u64
+    pub fn eval<T, I>(&self, args: I) -> Result<T, EvalError>
     where
-        I: Iterator<Item = u64>,
+        T: Value,
+        I: IntoIterator<Item = T>,
     {
+        let mut args = args.into_iter();
         match self {
-            Sum => args.fold_map(|x| x),
-            Prod => args.fold_map(frunk::semigroup::Product).0,
-            Min => args.fold_map_option(frunk::semigroup::Min).unwrap().0,
-            Max => args.fold_map_option(frunk::semigroup::Max).unwrap().0,
+            Sum => args.try_fold(T::zero(), |x, y| x.checked_add(&y)),
+            Prod => args.try_fold(T::one(), |x, y| x.checked_mul(&y)),
+            Min => return args.min().ok_or(EvalError::NoArguments(*self)),
+            Max => return args.max().ok_or(EvalError::NoArguments(*self)),
+        }
+        .ok_or(EvalError::Overflow)
+    }
+}
+
+impl From<ArbOp> for u8 {
+    fn from(op: ArbOp) -> Self {
+        match op {
+            Sum => 0,
+            Prod => 1,
+            Min => 2,
+            Max => 3,
         }
     }
 }
@@ -205,7 +314,7 @@ impl TryFrom<u8> for ArbOp {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BinOp {
     Gt,
     Lt,
@@ -213,11 +322,26 @@ pub enum BinOp {
 }
 
 impl BinOp {
-    pub fn eval(&self, arg1: u64, arg2: u64) -> u64 {
-        match self {
-            Gt => (arg1 > arg2) as u64,
-            Lt => (arg1 < arg2) as u64,
-            Eq => (arg1 == arg2) as u64,
+    pub fn eval<T: Value>(&self, arg1: &T, arg2: &T) -> T {
+        let b = match self {
+            Gt => arg1 > arg2,
+            Lt => arg1 < arg2,
+            Eq => arg1 == arg2,
+        };
+        if b {
+            T::one()
+        } else {
+            T::zero()
+        }
+    }
+}
+
+impl From<BinOp> for u8 {
+    fn from(op: BinOp) -> Self {
+        match op {
+            Gt => 5,
+            Lt => 6,
+            Eq => 7,
         }
     }
 }
@@ -235,139 +359,134 @@ impl TryFrom<u8> for BinOp {
     }
 }
 
-fn literal<'a>(id: u8) -> impl Parser<&'a [Binary], Output = Payload> {
-    if id != 4 {
-        return unexpected_any("bad opcode for literal").left();
-    }
-    fn go<'a>(
-        mut env: Vec<Binary>,
-        input: &mut &'a [Binary],
-    ) -> StdParseResult<Vec<Binary>, &'a [Binary]> {
-        let (bs, c) = take(5).parse_stream(input).into_result()?;
-        env.extend(bs[1..].iter());
-        if bs[0] == Zero {
-            Ok((env, c))
-        } else {
-            c.combine(|_| go(env, input))
+/// Parses the puzzle input: a single hexadecimal transmission holding
+/// one packet.
+pub fn parse_packet() -> impl Fn(&str) -> Result<(String, Packet), DecodeError> {
+    |s| {
+        let mut decoder = Decoder::from_hex(s.as_bytes());
+        let p = decoder.next().unwrap_or(Err(DecodeError::UnexpectedEof))?;
+        match decoder.next() {
+            None => Ok((String::new(), p)),
+            Some(Err(err)) => Err(err),
+            Some(Ok(_)) => Err(DecodeError::TrailingPackets),
         }
     }
-
-    let buf = Vec::new();
-    env_parser(buf, go)
-        .flat_map(|buf| {
-            Binary::try_to_u64(&buf)
-                .map(Literal)
-                .ok_or(UnexpectedParse::Unexpected)
-        })
-        .right()
 }
 
-fn binary_to_usize<'a>(n: usize) -> impl Parser<&'a [Binary], Output = usize> {
-    take(n).then(|size: &'a [Binary]| match Binary::try_to_usize(size) {
-        Some(size) => value(size).left(),
-        None => unexpected_any("small word size").right(),
-    })
-}
+#[cfg(test)]
+mod test_encode {
+    use quickcheck::{Arbitrary, Gen};
+
+    use super::*;
+
+    fn arbitrary_packet(g: &mut Gen, depth: usize) -> Packet {
+        let version = u8::arbitrary(g) % 8;
+        let payload = match u8::arbitrary(g) % if depth == 0 { 1 } else { 3 } {
+            0 => Literal(BigUint::from(u128::arbitrary(g))),
+            1 => {
+                let op = *g.choose(&[Sum, Prod, Min, Max]).unwrap();
+                let n = usize::arbitrary(g) % 4;
+                Arb(op, (0..n).map(|_| arbitrary_packet(g, depth - 1)).collect())
+            }
+            _ => {
+                let op = *g.choose(&[Gt, Lt, Eq]).unwrap();
+                let arg1 = arbitrary_packet(g, depth - 1);
+                let arg2 = arbitrary_packet(g, depth - 1);
+                Bin(op, Box::new(arg1), Box::new(arg2))
+            }
+        };
+        Packet { version, payload }
+    }
 
-fn arbop<'a>(id: u8) -> impl Parser<&'a [Binary], Output = Payload> {
-    let op = match ArbOp::try_from(id) {
-        Ok(op) => op,
-        Err(_) => return unexpected_any("bad opcode for arbop").left(),
-    };
-    any()
-        .then(move |mode| match mode {
-            Zero => binary_to_usize(15)
-                .then(move |size| {
-                    take(size)
-                        .flat_map(|bs| many(packet()).and(eof()).parse(bs).map(|p| p.0 .0))
-                        .map(move |bs| Arb(op, bs))
-                })
-                .left(),
-            One => binary_to_usize(11)
-                .then(move |size| count_min_max(size, size, packet()).map(move |bs| Arb(op, bs)))
-                .right(),
-        })
-        .right()
-}
+    impl Arbitrary for Packet {
+        fn arbitrary(g: &mut Gen) -> Self {
+            arbitrary_packet(g, 4)
+        }
+    }
 
-fn binop<'a>(id: u8) -> impl Parser<&'a [Binary], Output = Payload> {
-    let op = match BinOp::try_from(id) {
-        Ok(op) => op,
-        Err(_) => return unexpected_any("bad opcode for binop").left(),
-    };
-    any()
-        .then(move |mode| match mode {
-            Zero => binary_to_usize(15)
-                .then(move |size| {
-                    take(size).flat_map(move |bs| {
-                        packet()
-                            .and(packet())
-                            .and(eof())
-                            .parse(bs)
-                            .map(move |(((p1, p2), _), _)| Bin(op, Box::new(p1), Box::new(p2)))
-                    })
-                })
-                .left(),
-            One => binary_to_usize(11)
-                .then(move |size| match size {
-                    2 => packet()
-                        .and(packet())
-                        .map(move |(p1, p2)| Bin(op, Box::new(p1), Box::new(p2)))
-                        .left(),
-                    _ => unexpected_any("wrong number of arguments for binary operator").right(),
-                })
-                .right(),
-        })
-        .right()
-}
+    fn round_trip(p: &Packet, prefer: LengthType) -> Packet {
+        let hex = bits_to_hex(&p.to_bits_with(prefer).unwrap());
+        let (_, q) = parse_packet()(&format!("{}\n", hex)).unwrap();
+        q
+    }
 
-fn packet_<'a>() -> impl Parser<&'a [Binary], Output = Packet> {
-    take(3)
-        .and(take(3))
-        .then(|(version, id): (&'a [Binary], &'a [Binary])| {
-            // These can't fail: both arguments are guaranteed to have
-            // length 3 <= 8.
-            let version = Binary::try_to_u8(version).unwrap_or_else(|| unreachable!());
-            let id = Binary::try_to_u8(id).unwrap_or_else(|| unreachable!());
-            literal(id)
-                .or(arbop(id))
-                .or(binop(id))
-                .map(move |payload| Packet { version, payload })
-        })
-}
+    #[test]
+    fn test_encode_example() {
+        let (_, p) = parse_packet()("38006F45291200\n").unwrap();
+        let hex = bits_to_hex(&p.to_bits_with(LengthType::Bits).unwrap());
+        assert_eq!(hex, "38006F45291200");
 
-parser! {
-    fn packet['a]()(&'a [Binary]) -> Packet where [] {
-        packet_()
+        let (_, p) = parse_packet()("EE00D40C823060\n").unwrap();
+        assert_eq!(p.to_hex().unwrap(), "EE00D40C823060");
     }
-}
 
-fn top_level_packet<'a>() -> impl Parser<&'a [Binary], Output = Packet> {
-    packet()
-        .and(count_min_max::<Vec<_>, _, _>(0, 7, one_of([Zero])))
-        .and(eof())
-        .map(|p| p.0 .0)
-}
+    #[test]
+    fn test_encode_round_trip() {
+        fn prop(p: Packet) -> bool {
+            round_trip(&p, LengthType::Bits) == p && round_trip(&p, LengthType::Count) == p
+        }
+        quickcheck::quickcheck(prop as fn(Packet) -> bool);
+    }
 
-fn packet_from_chars<Input>() -> impl Parser<Input, Output = Packet>
-where
-    Input: Stream<Token = char, Error = UnexpectedParse>,
-    Input::Position: Default,
-{
-    many::<Vec<_>, _, _>(unhex())
-        .and(newline())
-        .and(eof())
-        .map(|p| p.0 .0.concat())
-        .flat_map(|bs| top_level_packet().parse(&bs).map(|p| p.0))
+    #[test]
+    fn test_encode_version_too_large() {
+        let p = Packet {
+            version: 8,
+            payload: Literal(BigUint::zero()),
+        };
+        assert_eq!(p.to_hex(), Err(EncodeError::VersionTooLarge(8)));
+    }
 }
 
-#[allow(clippy::let_and_return)]
-pub fn parse_packet() -> impl Fn(&str) -> Result<(String, Packet), UnexpectedParse> {
-    |s| {
-        let chrs = s.chars().collect_vec();
-        let x = packet_from_chars()
-            .parse(&chrs[..])
-            .map(|(p, s)| (s.iter().collect(), p));
-        x
+#[cfg(test)]
+mod test_eval {
+    use super::*;
+
+    fn packet(s: &str) -> Packet {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_eval_overflow() {
+        let p = packet("(prod 4294967296 4294967296)");
+        assert_eq!(p.eval::<u64>(), Err(EvalError::Overflow));
+        assert_eq!(p.eval::<u128>(), Ok(1 << 64));
+
+        let p = packet("(sum 18446744073709551616 1)");
+        assert_eq!(p.eval::<u64>(), Err(EvalError::Overflow));
+        assert_eq!(p.eval::<u128>(), Ok((1 << 64) + 1));
+    }
+
+    #[test]
+    fn test_eval_big() {
+        let p = packet("(prod 18446744073709551616 18446744073709551616 2)");
+        assert_eq!(p.eval::<u128>(), Err(EvalError::Overflow));
+        assert_eq!(p.eval::<BigUint>(), Ok(BigUint::one() << 129_u8));
+        assert_eq!(
+            packet("(gt (prod 18446744073709551616 18446744073709551616) 7)").eval::<BigUint>(),
+            Ok(BigUint::one())
+        );
+    }
+
+    #[test]
+    fn test_eval_no_arguments() {
+        assert_eq!(packet("(sum)").eval::<u64>(), Ok(0));
+        assert_eq!(packet("(prod)").eval::<u64>(), Ok(1));
+        assert_eq!(
+            packet("(sum 1 (min))").eval::<u64>(),
+            Err(EvalError::NoArguments(Min))
+        );
+        assert_eq!(
+            packet("(max)").eval::<u64>(),
+            Err(EvalError::NoArguments(Max))
+        );
+    }
+
+    #[test]
+    fn test_eval_wide_literal() {
+        // A literal that needs all 64 bits.
+        let p = packet("18446744073709551615");
+        let (_, q) = parse_packet()(&format!("{}\n", p.to_hex().unwrap())).unwrap();
+        assert_eq!(q.eval::<u64>(), Ok(u64::MAX));
     }
 }
@@ -2,7 +2,7 @@ use aoc2021::day_16::*;
 use aoc2021::*;
 
 fn run(input: Packet) -> u64 {
-    input.eval()
+    input.eval::<u64>().unwrap()
 }
 
 make_main! {16, parse_packet, run}
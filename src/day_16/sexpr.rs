@@ -0,0 +1,238 @@
+//! A textual form for packets, as S-expressions.
+//!
+//! Literals are written as plain numbers, and operators as
+//! parenthesized lists headed by the operator's name, which is
+//! separated from its arguments by whitespace:
+//!
+//! ```text
+//! (sum 3 (max 7 9) (gt 1 2))
+//! ```
+//!
+//! Versions are written after an `@`, as in `(sum@3 7@1 9)`, and
+//! default to 0 when omitted.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use combine::error::StringStreamError;
+use combine::parser::char::{char, letter, space, spaces};
+use combine::{between, eof, look_ahead, many, many1, optional, Parser};
+
+use super::ArbOp::*;
+use super::BinOp::*;
+use super::Payload::*;
+use super::{ArbOp, BinOp, Packet};
+use crate::combine_parse_integral_nonnegative;
+
+impl Display for ArbOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Sum => "sum",
+            Prod => "prod",
+            Min => "min",
+            Max => "max",
+        })
+    }
+}
+
+impl FromStr for ArbOp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sum" => Ok(Sum),
+            "prod" => Ok(Prod),
+            "min" => Ok(Min),
+            "max" => Ok(Max),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for BinOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Gt => "gt",
+            Lt => "lt",
+            Eq => "eq",
+        })
+    }
+}
+
+impl FromStr for BinOp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gt" => Ok(Gt),
+            "lt" => Ok(Lt),
+            "eq" => Ok(Eq),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Writes the packet as an S-expression, or with `{:#}`, as an infix
+/// expression such as `(3 + max(7, 9))`.  The infix form omits
+/// versions and cannot be parsed back.
+impl Display for Packet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return self.fmt_infix(f);
+        }
+        let version = |f: &mut Formatter<'_>| match self.version {
+            0 => Ok(()),
+            v => write!(f, "@{}", v),
+        };
+        match &self.payload {
+            Literal(n) => {
+                write!(f, "{}", n)?;
+                version(f)
+            }
+            Arb(op, args) => {
+                write!(f, "({}", op)?;
+                version(f)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                f.write_str(")")
+            }
+            Bin(op, arg1, arg2) => {
+                write!(f, "({}", op)?;
+                version(f)?;
+                write!(f, " {} {})", arg1, arg2)
+            }
+        }
+    }
+}
+
+impl Packet {
+    fn fmt_infix(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.payload {
+            Literal(n) => write!(f, "{}", n),
+            Arb(op @ (Sum | Prod), args) if !args.is_empty() => {
+                let sep = if *op == Sum { " + " } else { " * " };
+                f.write_str("(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(sep)?;
+                    }
+                    write!(f, "{:#}", arg)?;
+                }
+                f.write_str(")")
+            }
+            Arb(op, args) => {
+                write!(f, "{}(", op)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{:#}", arg)?;
+                }
+                f.write_str(")")
+            }
+            Bin(op, arg1, arg2) => {
+                let op = match op {
+                    Gt => ">",
+                    Lt => "<",
+                    Eq => "==",
+                };
+                write!(f, "({:#} {} {:#})", arg1, op, arg2)
+            }
+        }
+    }
+}
+
+fn version<'a>() -> impl Parser<&'a str, Output = u8> {
+    optional(char('@').with(combine_parse_integral_nonnegative())).map(|v| v.unwrap_or(0))
+}
+
+fn sexpr_<'a>() -> impl Parser<&'a str, Output = Packet> {
+    let literal = combine_parse_integral_nonnegative()
+        .and(version())
+        .map(|(n, version)| Packet {
+            version,
+            payload: Literal(n),
+        });
+    let operator = between(
+        char('(').skip(spaces()),
+        char(')'),
+        many1::<String, _, _>(letter())
+            .and(version())
+            // Otherwise `(sum3)` would read as `(sum 3)`.
+            .skip(look_ahead(space().or(char(')'))))
+            .skip(spaces())
+            .and(many::<Vec<_>, _, _>(sexpr())),
+    )
+    .flat_map(|((name, version), args)| {
+        let payload = if let Ok(op) = name.parse::<ArbOp>() {
+            Arb(op, args)
+        } else if let Ok(op) = name.parse::<BinOp>() {
+            match <[Packet; 2]>::try_from(args) {
+                Ok([arg1, arg2]) => Bin(op, Box::new(arg1), Box::new(arg2)),
+                Err(_) => return Err(StringStreamError::UnexpectedParse),
+            }
+        } else {
+            return Err(StringStreamError::UnexpectedParse);
+        };
+        Ok(Packet { version, payload })
+    });
+    literal.or(operator).skip(spaces())
+}
+
+combine::parser! {
+    fn sexpr['a]()(&'a str) -> Packet where [] {
+        sexpr_()
+    }
+}
+
+/// Parses a packet from its S-expression form.
+impl FromStr for Packet {
+    type Err = StringStreamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        spaces().with(sexpr()).skip(eof()).parse(s).map(|(p, _)| p)
+    }
+}
+
+#[cfg(test)]
+mod test_sexpr {
+    use super::*;
+    use crate::day_16::parse_packet;
+
+    #[test]
+    fn test_sexpr_example() {
+        let (_, p) = parse_packet()("9C0141080250320F1802104A08\n").unwrap();
+        assert_eq!(p.to_string(), "(eq@4 (sum@2 1@2 3@4) (prod@6 2 2@2))");
+        assert_eq!(format!("{:#}", p), "((1 + 3) == (2 * 2))");
+        assert_eq!("(eq@4 (sum@2 1@2 3@4) (prod@6 2 2@2))".parse(), Ok(p));
+    }
+
+    #[test]
+    fn test_sexpr_whitespace() {
+        let p = " ( sum 3\n  (max 7 9) ) ".parse::<Packet>().unwrap();
+        assert_eq!(p.to_string(), "(sum 3 (max 7 9))");
+        assert_eq!(p.eval::<u64>(), Ok(12));
+    }
+
+    #[test]
+    fn test_sexpr_errors() {
+        assert!("(gt 1)".parse::<Packet>().is_err());
+        assert!("(foo 1 2)".parse::<Packet>().is_err());
+        assert!("(sum 1 2".parse::<Packet>().is_err());
+        assert!("(sum3)".parse::<Packet>().is_err());
+        assert!("(sum@1(max 2))".parse::<Packet>().is_err());
+        assert_eq!(
+            "(sum@1)".parse::<Packet>().map(|p| p.to_string()),
+            Ok("(sum@1)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sexpr_round_trip() {
+        fn prop(p: Packet) -> bool {
+            p.to_string().parse() == Ok(p)
+        }
+        quickcheck::quickcheck(prop as fn(Packet) -> bool);
+    }
+}
@@ -0,0 +1,309 @@
+use std::io::{self, BufReader, ErrorKind, Read};
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+use super::{ArbOp, BinOp, Packet, Payload};
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(io::Error),
+    /// The transmission ended in the middle of a packet.
+    UnexpectedEof,
+    /// A byte in a hexadecimal transmission that is neither a hex
+    /// digit nor whitespace.
+    InvalidHex(u8),
+    /// The subpackets of an operator packet overran the length in
+    /// bits given in its header.
+    BadLength,
+    /// A binary operator packet with the wrong number of subpackets.
+    BadArity(BinOp, usize),
+    /// The padding after a packet contained a one bit.
+    NonZeroPadding,
+    /// More packets followed the one that was expected.
+    TrailingPackets,
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+/// Reads bits, most significant first, from a byte stream.
+///
+/// The stream can either be raw bytes, or ASCII hexadecimal digits
+/// (with any whitespace ignored).  It is read through a buffer, so
+/// unbuffered readers such as files don't need wrapping first.
+pub struct BitReader<R> {
+    inner: BufReader<R>,
+    hex: bool,
+    /// Bits that have been read from `inner` but not yet returned, in
+    /// the low `len` bits.
+    buf: u8,
+    len: u32,
+    /// The number of bits returned so far.
+    pos: usize,
+}
+
+impl<R: Read> BitReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: BufReader::new(inner),
+            hex: false,
+            buf: 0,
+            len: 0,
+            pos: 0,
+        }
+    }
+
+    pub fn from_hex(inner: R) -> Self {
+        Self {
+            hex: true,
+            ..Self::new(inner)
+        }
+    }
+
+    /// Returns the number of bits read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Refills the buffer, returning `false` at the end of the
+    /// stream.
+    fn fill(&mut self) -> Result<bool, DecodeError> {
+        let mut byte = [0];
+        loop {
+            match self.inner.read(&mut byte) {
+                Ok(0) => return Ok(false),
+                Ok(_) => (),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+            let [b] = byte;
+            if !self.hex {
+                self.buf = b;
+                self.len = 8;
+                return Ok(true);
+            }
+            if b.is_ascii_whitespace() {
+                continue;
+            }
+            let digit = (b as char).to_digit(16).ok_or(DecodeError::InvalidHex(b))?;
+            self.buf = digit as u8;
+            self.len = 4;
+            return Ok(true);
+        }
+    }
+
+    /// Indicates whether there are no bits left in the stream.
+    pub fn at_eof(&mut self) -> Result<bool, DecodeError> {
+        Ok(self.len == 0 && !self.fill()?)
+    }
+
+    pub fn read_bit(&mut self) -> Result<bool, DecodeError> {
+        if self.len == 0 && !self.fill()? {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        self.len -= 1;
+        self.pos += 1;
+        Ok((self.buf >> self.len) & 1 == 1)
+    }
+
+    /// Reads `n <= 64` bits as a big-endian number.
+    pub fn read_bits(&mut self, n: u32) -> Result<u64, DecodeError> {
+        (0..n).try_fold(0, |acc, _| Ok((acc << 1) | self.read_bit()? as u64))
+    }
+
+    /// Skips to the next multiple of 8 bits, checking that the skipped
+    /// bits are all zero.
+    pub fn skip_padding(&mut self) -> Result<(), DecodeError> {
+        while self.pos % 8 != 0 {
+            // The transmission may end partway through a byte (for
+            // example, with an odd number of hex digits).
+            if self.at_eof()? {
+                break;
+            }
+            if self.read_bit()? {
+                return Err(DecodeError::NonZeroPadding);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes a sequence of padded top-level packets from a bit stream,
+/// one at a time, without holding the whole transmission in memory.
+pub struct Decoder<R> {
+    reader: BitReader<R>,
+}
+
+impl<R: Read> Decoder<R> {
+    /// Decodes packets from raw bytes.
+    pub fn new(inner: R) -> Self {
+        Self {
+            reader: BitReader::new(inner),
+        }
+    }
+
+    /// Decodes packets from ASCII hexadecimal digits.
+    pub fn from_hex(inner: R) -> Self {
+        Self {
+            reader: BitReader::from_hex(inner),
+        }
+    }
+
+    fn packet(&mut self) -> Result<Packet, DecodeError> {
+        let version = self.reader.read_bits(3)? as u8;
+        let id = self.reader.read_bits(3)? as u8;
+        let payload = if id == 4 {
+            let mut n = BigUint::zero();
+            loop {
+                let more = self.reader.read_bit()?;
+                n = (n << 4_u8) | BigUint::from(self.reader.read_bits(4)?);
+                if !more {
+                    break Payload::Literal(n);
+                }
+            }
+        } else {
+            let args = self.operands()?;
+            match (ArbOp::try_from(id), BinOp::try_from(id)) {
+                (Ok(op), _) => Payload::Arb(op, args),
+                (_, Ok(op)) => match <[Packet; 2]>::try_from(args) {
+                    Ok([arg1, arg2]) => Payload::Bin(op, Box::new(arg1), Box::new(arg2)),
+                    Err(args) => return Err(DecodeError::BadArity(op, args.len())),
+                },
+                // This can't happen: every 3-bit id other than 4 is
+                // an operator.
+                _ => unreachable!(),
+            }
+        };
+        Ok(Packet { version, payload })
+    }
+
+    fn operands(&mut self) -> Result<Vec<Packet>, DecodeError> {
+        let mut args = Vec::new();
+        if self.reader.read_bit()? {
+            let count = self.reader.read_bits(11)?;
+            for _ in 0..count {
+                args.push(self.packet()?);
+            }
+        } else {
+            let len = self.reader.read_bits(15)? as usize;
+            let end = self.reader.position() + len;
+            while self.reader.position() < end {
+                args.push(self.packet()?);
+            }
+            if self.reader.position() != end {
+                return Err(DecodeError::BadLength);
+            }
+        }
+        Ok(args)
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = Result<Packet, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.at_eof() {
+            Ok(true) => return None,
+            Ok(false) => (),
+            Err(err) => return Some(Err(err)),
+        }
+        Some(self.packet().and_then(|p| {
+            self.reader.skip_padding()?;
+            Ok(p)
+        }))
+    }
+}
+
+/// Decodes a single packet from raw bytes.
+pub fn decode_bytes(bytes: &[u8]) -> Result<Packet, DecodeError> {
+    Decoder::new(bytes)
+        .next()
+        .unwrap_or(Err(DecodeError::UnexpectedEof))
+}
+
+#[cfg(test)]
+mod test_decoder {
+    use super::*;
+    use crate::day_16::parse_packet;
+
+    #[test]
+    fn test_decoder_round_trip() {
+        fn prop(p: Packet) -> bool {
+            let hex = p.to_hex().unwrap();
+            let line = format!("{}\n", hex);
+            let (_, q) = parse_packet()(&line).unwrap();
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect::<Vec<_>>();
+            let r = decode_bytes(&bytes).unwrap();
+            let mut decoder = Decoder::from_hex(line.as_bytes());
+            let s = decoder.next().unwrap().unwrap();
+            p == q && p == r && p == s && decoder.next().is_none()
+        }
+        quickcheck::quickcheck(prop as fn(Packet) -> bool);
+    }
+
+    #[test]
+    fn test_decoder_several_packets() {
+        let hex = "D2FE28 38006F45291200\nEE00D40C823060\n";
+        let packets = Decoder::from_hex(hex.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            packets.iter().map(Packet::to_string).collect::<Vec<_>>(),
+            vec!["2021@6", "(lt@1 10@6 20@2)", "(max@7 1@2 2@4 3@1)"]
+        );
+    }
+
+    #[test]
+    fn test_decoder_errors() {
+        assert!(matches!(
+            decode_bytes(&[0xD2, 0xFE]),
+            Err(DecodeError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            Decoder::from_hex("D2FE2G".as_bytes()).next(),
+            Some(Err(DecodeError::InvalidHex(b'G')))
+        ));
+        assert!(matches!(
+            Decoder::from_hex("D2FE29".as_bytes()).next(),
+            Some(Err(DecodeError::NonZeroPadding))
+        ));
+        assert!(matches!(
+            parse_packet()("D2FE28 D2FE28\n"),
+            Err(DecodeError::TrailingPackets)
+        ));
+        assert!(matches!(
+            parse_packet()("\n"),
+            Err(DecodeError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn test_decoder_buffers_reads() {
+        struct CountingReader<'a> {
+            inner: &'a [u8],
+            reads: usize,
+        }
+
+        impl Read for CountingReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.reads += 1;
+                self.inner.read(buf)
+            }
+        }
+
+        let hex = "D2FE28".repeat(1000);
+        let mut reader = CountingReader {
+            inner: hex.as_bytes(),
+            reads: 0,
+        };
+        assert_eq!(Decoder::from_hex(&mut reader).count(), 1000);
+        assert!(reader.reads <= 3, "{} reads", reader.reads);
+    }
+}
@@ -1,6 +1,11 @@
+use std::ops::RangeInclusive;
+
 use combine::Parser;
 use itertools::Itertools;
 
+use crate::triangular;
+use crate::vector::Vector;
+
 #[derive(Clone, Copy)]
 pub struct Input {
     x_lo: i32,
@@ -10,78 +15,203 @@ pub struct Input {
 }
 
 impl Input {
-    fn in_target(self, x: i32, y: i32) -> bool {
-        x >= self.x_lo && x <= self.x_hi && y >= self.y_lo && y <= self.y_hi
+    fn vx_range(self) -> RangeInclusive<i32> {
+        // After the first step, the probe's x-coordinate is vx₀, and it
+        // never moves back toward the launcher; so a feasible vx₀ can't
+        // overshoot the far side of the target x-range.
+        self.x_lo.min(0)..=self.x_hi.max(0)
     }
 
-    fn min_vx(self) -> i32 {
-        assert!(self.x_lo >= 0);
-        let x_lo = self.x_lo as f32;
-        // The minimum feasible vx₀ makes us just barely graze the
-        // left side of the target x-region before drag causes vx to
-        // become 0.  The final x-coordinate for a given vx₀ is
-        //
-        // vx₀ + (vx₀ - 1) + ⋯ + 1 = vx₀(vx₀ + 1)/2.
-        //
-        // Solving the equation vx₀(vx₀ + 1)/2 = x for vx₀ yields the
-        // (positive) solution vx₀ = (√(8x + 1) - 1)/2.
-        let vx = ((8.0 * x_lo + 1.0).sqrt() - 1.0) / 2.0;
-        vx.ceil() as i32
+    fn vy_range(self, last_x_step: i64) -> RangeInclusive<i32> {
+        // A negative vy₀ can't overshoot the bottom of the target
+        // y-region on the first step.
+        let min_vy = self.y_lo.min(0);
+        // A positive vy₀ passes through the same y-coordinates on the
+        // way down as on the way up: vy₀, ..., T(vy₀), ..., vy₀, 0
+        // (after step 2vy₀ + 1), and then -vy₀ - 1 and below.  So if
+        // the target is entirely below the launcher, the maximum
+        // feasible vy₀ makes us just barely hit its bottom; if it is
+        // entirely above, vy₀ can't overshoot its top on the first
+        // step; and if it contains y = 0, a larger vy₀ can only hit
+        // it at y = 0, which had better happen while we are still in
+        // the target x-range.
+        let max_vy = if self.y_hi < 0 {
+            -(self.y_lo + 1)
+        } else if self.y_lo > 0 {
+            self.y_hi
+        } else {
+            let last = (last_x_step.saturating_sub(1) / 2).min(i32::MAX as i64) as i32;
+            self.y_hi.max(-self.y_lo - 1).max(last)
+        };
+        min_vy..=max_vy
     }
 
-    fn max_vx(self) -> i32 {
-        assert!(self.x_hi >= 0);
-        self.x_hi
+    /// Steps after which the probe's x-coordinate lies in the target
+    /// x-range, given its initial x-velocity.
+    ///
+    /// For `vx₀ ≥ 0`, drag makes the probe's x-coordinate after `t`
+    /// steps `T(vx₀) - T(vx₀ - t)` while `t ≤ vx₀`, where `T` is the
+    /// triangular function, and `T(vx₀)` afterward; so the steps form
+    /// a single, possibly unbounded, range.
+    fn x_steps(self, vx: i32) -> Option<RangeInclusive<i64>> {
+        if vx < 0 {
+            // Mirror everything.
+            let mirror = Self {
+                x_lo: -self.x_hi,
+                x_hi: -self.x_lo,
+                ..self
+            };
+            return mirror.x_steps(-vx);
+        }
+        let vx = vx as i64;
+        let total = triangular(vx);
+        let (x_lo, x_hi) = (self.x_lo as i64, self.x_hi as i64);
+        if total < x_lo {
+            return None;
+        }
+        let start = vx - triangular_inverse(total - x_lo);
+        let end = if total <= x_hi {
+            i64::MAX
+        } else {
+            vx - triangular_inverse(total - x_hi - 1) - 1
+        };
+        intersect(&(start..=end), &(1..=i64::MAX))
     }
 
-    fn min_vy(self) -> i32 {
-        assert!(self.y_lo <= 0);
-        self.y_lo
-    }
+    /// Steps after which the probe's y-coordinate lies in the target
+    /// y-range, given its initial y-velocity.
+    ///
+    /// The probe's y-coordinate after `t` steps is
+    /// `T(vy₀) - T(t - vy₀ - 1)`, which is symmetric about
+    /// `t = vy₀ + ½`; so the steps form at most two ranges, one on the
+    /// way up and one on the way down.
+    fn y_steps(self, vy: i32) -> Vec<RangeInclusive<i64>> {
+        // Steps after which the y-coordinate is at least y.
+        fn at_least(vy: i64, y: i64) -> Option<RangeInclusive<i64>> {
+            let m = triangular(vy) - y;
+            if m < 0 {
+                return None;
+            }
+            let j = triangular_inverse(m);
+            Some(vy - j..=vy + 1 + j)
+        }
 
-    fn max_vy(self) -> i32 {
-        assert!(self.y_lo <= 0);
-        // The maximum feasible vy₀ makes us just barely hit the
-        // bottom of the target y-region on the way down (rather than
-        // simply falling past it).  Note that y-coordinates are
-        // symmetrical on the way up and the way down.
-        -self.y_lo - 1
+        let vy = vy as i64;
+        let (y_lo, y_hi) = (self.y_lo as i64, self.y_hi as i64);
+        let above_lo = match at_least(vy, y_lo) {
+            None => return Vec::new(),
+            Some(r) => r,
+        };
+        let below_hi = match at_least(vy, y_hi + 1) {
+            None => vec![1..=i64::MAX],
+            Some(r) => vec![1..=r.start() - 1, r.end() + 1..=i64::MAX],
+        };
+        below_hi
+            .iter()
+            .filter_map(|r| intersect(r, &above_lo))
+            .collect()
     }
 
-    fn x_orbit(vx: i32) -> impl Iterator<Item = i32> {
-        itertools::iterate((0, vx), |(x, vx)| (x + vx, (vx - 1).max(0))).map(|(x, _)| x)
+    /// Returns every launch velocity that hits the target.
+    ///
+    /// Rather than simulating each launch, this intersects the steps
+    /// at which each x-velocity is in the target x-range with the
+    /// steps at which each y-velocity is in the target y-range.
+    pub fn launches(self) -> Result<Vec<Launch>, TargetError> {
+        if self.x_lo > self.x_hi || self.y_lo > self.y_hi {
+            return Err(TargetError::Empty);
+        }
+        let x_steps = self
+            .vx_range()
+            .filter_map(|vx| Some((vx, self.x_steps(vx)?)))
+            .collect_vec();
+        let last_x_step = x_steps.iter().map(|(_, xs)| *xs.end()).max();
+        // If the probe can come to rest horizontally inside the target
+        // x-range, then any sufficiently large vy₀ will eventually
+        // bring it back down through y = 0.
+        if last_x_step == Some(i64::MAX) && self.y_lo <= 0 && self.y_hi >= 0 {
+            return Err(TargetError::Unbounded);
+        }
+        let mut ret = Vec::new();
+        for vy in self.vy_range(last_x_step.unwrap_or(0)) {
+            let y_steps = self.y_steps(vy);
+            for (vx, xs) in x_steps.iter() {
+                if let Some(hit) = y_steps.iter().find_map(|ys| intersect(xs, ys)) {
+                    let hit_step =
+                        u32::try_from(*hit.start()).map_err(|_| TargetError::TooManySteps)?;
+                    ret.push(Launch {
+                        velocity: Vector::from([*vx, vy]),
+                        apogee: triangular(vy.max(0) as i64),
+                        hit_step,
+                    });
+                }
+            }
+        }
+        Ok(ret)
     }
 
-    fn y_orbit(vy: i32) -> impl Iterator<Item = i32> {
-        itertools::iterate((0, vy), |(y, vy)| (y + vy, (vy - 1))).map(|(y, _)| y)
+    pub fn max_apogee(self) -> Result<i64, TargetError> {
+        // Every nonempty target can be hit: (vx₀, vy₀) = (x, y) puts
+        // the probe at (x, y) after one step.
+        Ok(self
+            .launches()?
+            .iter()
+            .map(|l| l.apogee)
+            .max()
+            .unwrap_or_else(|| unreachable!()))
     }
 
-    fn orbit(vx: i32, vy: i32) -> impl Iterator<Item = (i32, i32)> {
-        Input::x_orbit(vx).zip(Input::y_orbit(vy))
+    pub fn valid_velocities(self) -> Result<impl Iterator<Item = Vector<i32, 2>>, TargetError> {
+        Ok(self.launches()?.into_iter().map(|l| l.velocity))
     }
+}
 
-    fn is_valid(self, vx: i32, vy: i32) -> bool {
-        Input::orbit(vx, vy)
-            .take_while(|(x, y)| *x <= self.x_hi && *y >= self.y_lo)
-            .any(|(x, y)| self.in_target(x, y))
-    }
+/// Reasons that the launches for a target area can't be listed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TargetError {
+    /// One of the target's ranges has its ends reversed.
+    Empty,
+    /// Infinitely many launch velocities hit the target.
+    Unbounded,
+    /// Some launch only hits the target after more than `u32::MAX`
+    /// steps.
+    TooManySteps,
+}
 
-    pub fn max_apogee(self) -> i32 {
-        for vy in (self.min_vy()..=self.max_vy()).rev() {
-            for vx in self.min_vx()..=self.max_vx() {
-                if self.is_valid(vx, vy) {
-                    return vy * (vy + 1) / 2;
-                }
-            }
-        }
-        panic!("no solution")
-    }
+/// A launch that hits the target area.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Launch {
+    /// The initial velocity, as `(vx₀, vy₀)`.
+    pub velocity: Vector<i32, 2>,
+    /// The highest y-coordinate the probe reaches.
+    pub apogee: i64,
+    /// The first step after which the probe is in the target area.
+    pub hit_step: u32,
+}
 
-    pub fn valid_velocities(self) -> impl Iterator<Item = (i32, i32)> {
-        (self.min_vx()..=self.max_vx())
-            .cartesian_product(self.min_vy()..=self.max_vy())
-            .filter(move |(vx, vy)| self.is_valid(*vx, *vy))
+/// The largest `k >= 0` such that `T(k) <= n`, where `T` is the
+/// triangular function.  Requires `n >= 0`.
+fn triangular_inverse(n: i64) -> i64 {
+    // Neither 8n + 1 nor T(k + 1) fits in an i64 when n is close to
+    // i64::MAX, so work in i128.
+    let n = n as i128;
+    // Start from the floating-point solution of k(k + 1)/2 = n, and
+    // then correct for rounding.
+    let mut k = (((8 * n + 1) as f64).sqrt() as i128 - 1) / 2;
+    while triangular(k + 1) <= n {
+        k += 1;
     }
+    while triangular(k) > n {
+        k -= 1;
+    }
+    // This can't fail: T(2³²) > i64::MAX >= n, so k < 2³².
+    i64::try_from(k).unwrap_or_else(|_| unreachable!())
+}
+
+fn intersect(r1: &RangeInclusive<i64>, r2: &RangeInclusive<i64>) -> Option<RangeInclusive<i64>> {
+    let start = *r1.start().max(r2.start());
+    let end = *r1.end().min(r2.end());
+    (start <= end).then(|| start..=end)
 }
 
 pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
@@ -111,3 +241,146 @@ pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
             y_hi,
         })
 }
+
+#[cfg(test)]
+mod test_launches {
+    use super::*;
+
+    const EXAMPLE: Input = Input {
+        x_lo: 20,
+        x_hi: 30,
+        y_lo: -10,
+        y_hi: -5,
+    };
+
+    /// Simulates a launch, returning its first hit step.
+    fn simulate(input: Input, mut velocity: Vector<i32, 2>) -> Option<u32> {
+        let mut position = Vector::default();
+        for t in 1.. {
+            position += velocity;
+            velocity -= Vector::from([velocity[0].signum(), 1]);
+            let [x, y] = position.into_array();
+            if (input.x_lo..=input.x_hi).contains(&x) && (input.y_lo..=input.y_hi).contains(&y) {
+                return Some(t);
+            }
+            if y < input.y_lo && velocity[1] < 0 {
+                return None;
+            }
+            if t > 1000 {
+                panic!("simulation did not terminate");
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn test_launches_errors() {
+        let input = Input {
+            x_lo: -2,
+            x_hi: 3,
+            y_lo: -1,
+            y_hi: 1,
+        };
+        assert_eq!(input.launches(), Err(TargetError::Unbounded));
+        // The probe comes to rest at x = 6.
+        let input = Input {
+            x_lo: 5,
+            x_hi: 8,
+            y_lo: 0,
+            y_hi: 0,
+        };
+        assert_eq!(input.launches(), Err(TargetError::Unbounded));
+        let input = Input {
+            x_lo: 3,
+            x_hi: 2,
+            y_lo: -5,
+            y_hi: -2,
+        };
+        assert_eq!(input.max_apogee(), Err(TargetError::Empty));
+    }
+
+    fn check_against_simulation(input: Input) {
+        let launches = input.launches().unwrap();
+        let mut expected = Vec::new();
+        for vx in -100..=100 {
+            for vy in -100..=100 {
+                if let Some(hit_step) = simulate(input, Vector::from([vx, vy])) {
+                    expected.push((vx, vy, hit_step));
+                }
+            }
+        }
+        let mut actual = launches
+            .iter()
+            .map(|l| (l.velocity[0], l.velocity[1], l.hit_step))
+            .collect_vec();
+        actual.sort_unstable();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_triangular_inverse() {
+        for k in 0..100 {
+            assert_eq!(triangular_inverse(triangular(k)), k);
+            assert_eq!(triangular_inverse(triangular(k + 1) - 1), k);
+        }
+        // T(2³² - 1) = 2⁶³ - 2³¹, the largest triangular number that
+        // fits in an i64.
+        assert_eq!(triangular_inverse(9_223_372_034_707_292_160), 4_294_967_295);
+        assert_eq!(triangular_inverse(9_223_372_034_707_292_159), 4_294_967_294);
+        assert_eq!(triangular_inverse(i64::MAX), 4_294_967_295);
+    }
+
+    #[test]
+    fn test_launches_example() {
+        assert_eq!(EXAMPLE.launches().unwrap().len(), 112);
+        assert_eq!(EXAMPLE.max_apogee(), Ok(45));
+        check_against_simulation(EXAMPLE);
+    }
+
+    #[test]
+    fn test_launches_deep() {
+        let input = Input {
+            x_lo: 20,
+            x_hi: 30,
+            y_lo: -100_000,
+            y_hi: -99_990,
+        };
+        assert_eq!(input.max_apogee(), Ok(4_999_950_000));
+        let launches = input.launches().unwrap();
+        assert!(launches.iter().all(|l| l.apogee >= 0));
+        assert_eq!(launches.iter().map(|l| l.velocity[1]).max(), Some(99_999));
+    }
+
+    #[test]
+    fn test_launches_simulation() {
+        for (x_lo, x_hi) in [
+            (0, 0),
+            (1, 5),
+            (7, 7),
+            (11, 14),
+            (15, 40),
+            (-9, -4),
+            (-40, -31),
+            (4, 4),
+        ] {
+            // Whether the probe can come to rest horizontally, at some
+            // x = ±T(k), inside the target x-range.
+            let rests = (0..=10)
+                .map(triangular)
+                .any(|x| (x_lo..=x_hi).contains(&x) || (x_lo..=x_hi).contains(&-x));
+            for (y_lo, y_hi) in [(-1, -1), (-12, -3), (-30, -29), (1, 1), (3, 14), (-2, 2)] {
+                let input = Input {
+                    x_lo,
+                    x_hi,
+                    y_lo,
+                    y_hi,
+                };
+                if rests && y_lo <= 0 && y_hi >= 0 {
+                    assert_eq!(input.launches(), Err(TargetError::Unbounded));
+                } else {
+                    check_against_simulation(input);
+                }
+            }
+        }
+    }
+}
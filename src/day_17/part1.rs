@@ -1,7 +1,7 @@
 use aoc2021::day_17::Input;
 
-fn run(input: Input) -> i32 {
-    input.max_apogee()
+fn run(input: Input) -> i64 {
+    input.max_apogee().unwrap()
 }
 
 aoc2021::make_main_combine!(17, aoc2021::day_17::parse_input, run);
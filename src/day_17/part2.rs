@@ -1,7 +1,7 @@
 use aoc2021::day_17::Input;
 
 fn run(input: Input) -> usize {
-    input.valid_velocities().count()
+    input.valid_velocities().unwrap().count()
 }
 
 aoc2021::make_main_combine!(17, aoc2021::day_17::parse_input, run);
@@ -0,0 +1,139 @@
+use std::fmt::{self, Display, Formatter};
+use std::ops::Add;
+
+use itertools::Itertools;
+
+use super::{Number, Snailfish, Tree};
+use crate::zipper::Node;
+
+/// A snailfish number stored as its leaves, from left to right, each
+/// tagged with its depth (the number of pairs that contain it).
+///
+/// Adding and reducing these only shuffles a short vector around,
+/// which is much cheaper than cloning and rebuilding a `Number`'s
+/// tree.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FlatNumber {
+    leaves: Vec<(u32, u8)>,
+}
+
+impl FlatNumber {
+    /// Explodes the leftmost pair of regular numbers nested inside
+    /// four pairs, if any.
+    fn explode_first(&mut self) -> bool {
+        // The leftmost two adjacent leaves at the same depth are
+        // always a pair; later ones may just be cousins.  Unreduced
+        // input can nest deeper than five, so this can't just take
+        // the first deep leaf.
+        let i = match self
+            .leaves
+            .iter()
+            .tuple_windows()
+            .position(|(&(_, d1), &(_, d2))| d1 > 4 && d1 == d2)
+        {
+            None => return false,
+            Some(i) => i,
+        };
+        let (l, depth) = self.leaves[i];
+        let (r, _) = self.leaves.remove(i + 1);
+        if let Some((n, _)) = i.checked_sub(1).map(|j| &mut self.leaves[j]) {
+            *n += l;
+        }
+        if let Some((n, _)) = self.leaves.get_mut(i + 1) {
+            *n += r;
+        }
+        self.leaves[i] = (0, depth - 1);
+        true
+    }
+
+    /// Splits the leftmost regular number that is at least 10, if
+    /// any.
+    fn split_first(&mut self) -> bool {
+        let i = match self.leaves.iter().position(|&(n, _)| n >= 10) {
+            None => return false,
+            Some(i) => i,
+        };
+        let (n, depth) = self.leaves[i];
+        let l = n / 2;
+        self.leaves[i] = (l, depth + 1);
+        self.leaves.insert(i + 1, (n - l, depth + 1));
+        true
+    }
+
+    fn reduce(mut self) -> Self {
+        while self.explode_first() || self.split_first() {}
+        self
+    }
+}
+
+impl Add for FlatNumber {
+    type Output = FlatNumber;
+
+    fn add(mut self, rhs: FlatNumber) -> Self {
+        self.leaves.extend(rhs.leaves);
+        for (_, depth) in self.leaves.iter_mut() {
+            *depth += 1;
+        }
+        self.reduce()
+    }
+}
+
+impl Snailfish for FlatNumber {
+    fn magnitude(&self) -> u32 {
+        // Merge adjacent leaves of equal depth (which must be
+        // siblings) until only the root is left.
+        let mut stack = Vec::<(u32, u8)>::new();
+        for &(mut n, mut depth) in self.leaves.iter() {
+            while let Some(&(l, d)) = stack.last() {
+                if d != depth {
+                    break;
+                }
+                stack.pop();
+                n = 3 * l + 2 * n;
+                depth -= 1;
+            }
+            stack.push((n, depth));
+        }
+        stack[0].0
+    }
+}
+
+impl From<&Number> for FlatNumber {
+    fn from(number: &Number) -> Self {
+        fn go(tree: &Tree, depth: u8, leaves: &mut Vec<(u32, u8)>) {
+            match tree.as_ref() {
+                Node::Leaf(n) => leaves.push((*n, depth)),
+                Node::Branch(l, r) => {
+                    go(l, depth + 1, leaves);
+                    go(r, depth + 1, leaves);
+                }
+            }
+        }
+        let mut leaves = Vec::new();
+        go(&number.0, 0, &mut leaves);
+        Self { leaves }
+    }
+}
+
+impl From<&FlatNumber> for Number {
+    fn from(number: &FlatNumber) -> Self {
+        // Same as `magnitude`, but building trees.
+        let mut stack = Vec::<(Tree, u8)>::new();
+        for &(n, mut depth) in number.leaves.iter() {
+            let mut tree = Box::new(Node::Leaf(n));
+            while matches!(stack.last(), Some((_, d)) if *d == depth) {
+                let (l, _) = stack.pop().unwrap_or_else(|| unreachable!());
+                tree = Box::new(Node::Branch(l, tree));
+                depth -= 1;
+            }
+            stack.push((tree, depth));
+        }
+        Number(stack.remove(0).0)
+    }
+}
+
+impl Display for FlatNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Number::from(self).fmt(f)
+    }
+}
@@ -1,28 +1,61 @@
-mod tree;
+mod flat;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Sum;
 use std::ops::Add;
+use std::str::FromStr;
 
 use combine::error::StringStreamError;
 use combine::Parser;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use self::tree::*;
-use self::Node::*;
+pub use self::flat::FlatNumber;
+pub use crate::zipper::Direction;
+use crate::zipper::Node::*;
+use crate::zipper::Zipper;
+
+type Tree = crate::zipper::Tree<u32>;
+
+/// Operations shared by the representations of snailfish numbers.
+pub trait Snailfish: Clone + Add<Output = Self> + Send + Sync {
+    fn magnitude(&self) -> u32;
+}
+
+/// Returns the largest magnitude of the sum of two different numbers
+/// from the list, checking the pairs in parallel.
+pub fn max_pair_magnitude<N: Snailfish>(numbers: &[N]) -> Option<u32> {
+    (0..numbers.len())
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            (0..numbers.len())
+                .filter(move |&j| j != i)
+                .map(move |j| (numbers[i].clone() + numbers[j].clone()).magnitude())
+        })
+        .max()
+}
+
+/// A single step of reduction, with the path to the pair that exploded
+/// or the number that split.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    Explode(Vec<Direction>),
+    Split(Vec<Direction>),
+}
 
 #[derive(Clone, Debug)]
 pub struct Number(Tree);
 
 impl Number {
     pub fn repr(&self) -> String {
-        fn go(tree: &Tree) -> String {
-            match tree {
-                box Leaf(n) => n.to_string(),
-                box Branch(l, r) => format!("[{},{}]", go(l), go(r)),
-            }
-        }
-        go(&self.0)
+        self.to_string()
+    }
+
+    /// Pairs up two numbers without reducing the result.
+    pub fn join(self, rhs: Number) -> Self {
+        Number(box Branch(self.0, rhs.0))
     }
 
     // I decided I was bored of nice composable functional stuff. :D
-    fn reduce(self) -> Self {
+    pub fn reduce(self) -> Self {
         let mut zipper = Zipper::from(self.0);
         // Outer loop: repeat explode/split steps until we reach a
         // fixed point.
@@ -40,6 +73,13 @@ impl Number {
                             *n += r;
                         }
                         *zipper.focus_mut() = box Leaf(0);
+                        // In unreduced input, the parent may now be a
+                        // pair of regular numbers that needs to
+                        // explode before anything to the right.
+                        if zipper.depth() > 4 {
+                            zipper.focus_up();
+                            continue;
+                        }
                     }
                 }
                 zipper.focus_next_depth_first();
@@ -63,7 +103,70 @@ impl Number {
         Self(Tree::from(zipper))
     }
 
-    pub fn magnitude(&self) -> u32 {
+    /// Reduces one step at a time, yielding the action taken at each
+    /// step together with the resulting number.
+    ///
+    /// This is much slower than `reduce`, which does as many explodes
+    /// as it can in a single pass.
+    pub fn reduce_trace(self) -> impl Iterator<Item = (Action, Number)> {
+        let mut number = self;
+        std::iter::from_fn(move || {
+            let action = number.step()?;
+            Some((action, number.clone()))
+        })
+    }
+
+    /// Performs the leftmost explode, or if there is none, the
+    /// leftmost split.
+    fn step(&mut self) -> Option<Action> {
+        crate::take_return(&mut self.0, |tree| {
+            let mut zipper = Zipper::from(tree);
+            let action = Self::explode_first(&mut zipper).or_else(|| {
+                zipper.focus_top();
+                Self::split_first(&mut zipper)
+            });
+            (action, Tree::from(zipper))
+        })
+    }
+
+    fn explode_first(zipper: &mut Zipper<u32>) -> Option<Action> {
+        loop {
+            if let box Branch(box Leaf(l), box Leaf(r)) = zipper.focus() {
+                if zipper.depth() >= 4 {
+                    let l = *l;
+                    let r = *r;
+                    if let Some(n) = zipper.left_leaf_mut() {
+                        *n += l;
+                    }
+                    if let Some(n) = zipper.right_leaf_mut() {
+                        *n += r;
+                    }
+                    *zipper.focus_mut() = box Leaf(0);
+                    return Some(Action::Explode(zipper.path()));
+                }
+            }
+            zipper.focus_next_depth_first();
+            if zipper.at_top() {
+                return None;
+            }
+        }
+    }
+
+    fn split_first(zipper: &mut Zipper<u32>) -> Option<Action> {
+        while let Some(n) = zipper.focus_next_leaf_depth_first() {
+            if *n >= 10 {
+                let l = *n / 2;
+                let r = *n - l;
+                *zipper.focus_mut() = box Branch(box Leaf(l), box Leaf(r));
+                return Some(Action::Split(zipper.path()));
+            }
+        }
+        None
+    }
+}
+
+impl Snailfish for Number {
+    fn magnitude(&self) -> u32 {
         fn go(tree: &Tree) -> u32 {
             match tree {
                 box Leaf(n) => *n,
@@ -78,7 +181,42 @@ impl Add for Number {
     type Output = Number;
 
     fn add(self, rhs: Number) -> Self {
-        Number(box Branch(self.0, rhs.0)).reduce()
+        self.join(rhs).reduce()
+    }
+}
+
+/// Panics if the iterator is empty, since snailfish addition has no
+/// identity.
+impl Sum for Number {
+    fn sum<I: Iterator<Item = Number>>(iter: I) -> Self {
+        iter.reduce(Add::add)
+            .expect("can't sum an empty list of snailfish numbers")
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fn go(tree: &Tree, f: &mut Formatter<'_>) -> fmt::Result {
+            match tree {
+                box Leaf(n) => write!(f, "{}", n),
+                box Branch(l, r) => {
+                    f.write_str("[")?;
+                    go(l, f)?;
+                    f.write_str(",")?;
+                    go(r, f)?;
+                    f.write_str("]")
+                }
+            }
+        }
+        go(&self.0, f)
+    }
+}
+
+impl FromStr for Number {
+    type Err = StringStreamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_number().skip(combine::eof()).parse(s).map(|(n, _)| n)
     }
 }
 
@@ -105,7 +243,11 @@ combine::parser! {
 }
 
 fn parse_number<'a>() -> impl Parser<&'a str, Output = Number> {
-    parse_tree().map(Number)
+    // A snailfish number is always a pair.
+    parse_tree().flat_map(|tree| match tree {
+        box Branch(_, _) => Ok(Number(tree)),
+        box Leaf(_) => Err(StringStreamError::UnexpectedParse),
+    })
 }
 
 fn parse_numbers<'a>() -> impl Parser<&'a str, Output = Vec<Number>> {
@@ -124,3 +266,125 @@ pub fn parse_input() -> impl Fn(&str) -> Result<(String, Vec<Number>), StringStr
         x
     }
 }
+
+#[cfg(test)]
+mod test_number {
+    use super::Direction::*;
+    use super::*;
+
+    fn number(s: &str) -> Number {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_reduce_trace_example() {
+        let n = number("[[[[4,3],4],4],[7,[[8,4],9]]]").join(number("[1,1]"));
+        let trace = n
+            .reduce_trace()
+            .map(|(action, n)| (action, n.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            trace,
+            vec![
+                (
+                    Action::Explode(vec![Left, Left, Left, Left]),
+                    "[[[[0,7],4],[7,[[8,4],9]]],[1,1]]".to_string()
+                ),
+                (
+                    Action::Explode(vec![Left, Right, Right, Left]),
+                    "[[[[0,7],4],[15,[0,13]]],[1,1]]".to_string()
+                ),
+                (
+                    Action::Split(vec![Left, Right, Left]),
+                    "[[[[0,7],4],[[7,8],[0,13]]],[1,1]]".to_string()
+                ),
+                (
+                    Action::Split(vec![Left, Right, Right, Right]),
+                    "[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]".to_string()
+                ),
+                (
+                    Action::Explode(vec![Left, Right, Right, Right]),
+                    "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sum_example() {
+        let n = ["[1,1]", "[2,2]", "[3,3]", "[4,4]", "[5,5]", "[6,6]"]
+            .into_iter()
+            .map(number)
+            .sum::<Number>();
+        assert_eq!(n.to_string(), "[[[[5,0],[7,4]],[5,5]],[6,6]]");
+    }
+
+    #[test]
+    fn test_reduce_trace_matches_reduce() {
+        let n = number("[[[0,[4,5]],[0,0]],[[[4,5],[2,6]],[9,5]]]")
+            .join(number("[7,[[[3,7],[4,3]],[[6,3],[8,8]]]]"));
+        let (_, last) = n.clone().reduce_trace().last().unwrap();
+        assert_eq!(last.to_string(), n.reduce().to_string());
+        assert_eq!(
+            last.to_string(),
+            "[[[[4,0],[5,4]],[[7,7],[6,0]]],[[8,[7,7]],[[7,9],[5,0]]]]"
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(number("[[1,2],3]").to_string(), "[[1,2],3]");
+        assert!("3".parse::<Number>().is_err());
+        assert!("[1,2]x".parse::<Number>().is_err());
+    }
+
+    const HOMEWORK: [&str; 10] = [
+        "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]",
+        "[[[5,[2,8]],4],[5,[[9,9],0]]]",
+        "[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]",
+        "[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]",
+        "[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]",
+        "[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]",
+        "[[[[5,4],[7,7]],8],[[8,3],8]]",
+        "[[9,3],[[9,9],[6,[4,9]]]]",
+        "[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]",
+        "[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]",
+    ];
+
+    #[test]
+    fn test_flat_homework() {
+        let numbers = HOMEWORK.iter().map(|s| number(s)).collect::<Vec<_>>();
+        let flats = numbers.iter().map(FlatNumber::from).collect::<Vec<_>>();
+        for (n, f) in numbers.iter().zip(&flats) {
+            assert_eq!(f.to_string(), n.to_string());
+            assert_eq!(f.magnitude(), n.magnitude());
+        }
+
+        let sum = numbers.iter().cloned().sum::<Number>();
+        let flat_sum = flats.iter().cloned().reduce(Add::add).unwrap();
+        assert_eq!(
+            flat_sum.to_string(),
+            "[[[[6,6],[7,6]],[[7,7],[7,0]]],[[[7,7],[7,7]],[[7,8],[9,9]]]]"
+        );
+        assert_eq!(flat_sum.to_string(), sum.to_string());
+        assert_eq!(flat_sum.magnitude(), 4140);
+
+        assert_eq!(max_pair_magnitude(&numbers), Some(3993));
+        assert_eq!(max_pair_magnitude(&flats), Some(3993));
+    }
+
+    #[test]
+    fn test_flat_unreduced() {
+        for (a, b) in [
+            ("[[[[[1,[2,3]],4],5],6],7]", "[1,1]"),
+            ("[[[[[[1,2],[3,4]],5],6],7],8]", "[[[[9,9],9],9],9]"),
+            ("[[1,[[[[2,[3,4]],5],6],7]],8]", "[[[[[5,6],7],8],9],0]"),
+        ] {
+            let (a, b) = (number(a), number(b));
+            let flat_sum = FlatNumber::from(&a) + FlatNumber::from(&b);
+            let (_, stepped) = a.clone().join(b.clone()).reduce_trace().last().unwrap();
+            assert_eq!(flat_sum.to_string(), stepped.to_string());
+            assert_eq!(flat_sum.to_string(), (a + b).to_string());
+        }
+    }
+}
@@ -1,21 +1,8 @@
 use aoc2021::day_18::*;
-use itertools::Itertools;
 
 fn run(input: Vec<Number>) -> u32 {
-    let other = input.clone();
-    input
-        .into_iter()
-        .enumerate()
-        .cartesian_product(other.into_iter().enumerate())
-        .filter_map(|((i, x), (j, y))| {
-            if i != j {
-                Some((x + y).magnitude())
-            } else {
-                None
-            }
-        })
-        .max()
-        .unwrap()
+    let input = input.iter().map(FlatNumber::from).collect::<Vec<_>>();
+    max_pair_magnitude(&input).unwrap()
 }
 
 aoc2021::make_main! {18, parse_input, run}
@@ -1,3 +1,6 @@
+mod rotation;
+#[cfg(test)]
+mod scenario;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::sync::Mutex;
@@ -5,31 +8,12 @@ use std::sync::Mutex;
 use combine::Parser;
 use counter::Counter;
 use itertools::Itertools;
-use nalgebra::{IsometryMatrix3, Matrix3, Point3, Rotation3, Translation3};
+use nalgebra::{Point3, Vector3};
 use petgraph::graph::NodeIndex;
 use petgraph::Graph;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
-lazy_static::lazy_static! {
-    static ref ROTATIONS: [Rotation3<f32>; 24] = itertools::iproduct!(
-        [1_f32, -1_f32].into_iter(),
-        [1_f32, -1_f32].into_iter(),
-        [1_f32, -1_f32].into_iter(),
-        [0, 1, 2].into_iter().permutations(3)
-    )
-    .map(|(sgn1, sgn2, sgn3, ixs)| {
-        let mut m = Matrix3::zeros();
-        for (i, (sgn, j)) in [sgn1, sgn2, sgn3].iter().copied().zip(ixs).enumerate() {
-            m[(i, j)] = sgn;
-        }
-        m
-    })
-    .filter(|m| m.determinant() == 1_f32)
-    .map(Rotation3::from_matrix_unchecked)
-    .collect_vec()
-    .try_into()
-    .unwrap_or_else(|_| unreachable!());
-}
+pub use self::rotation::{Isometry, Rotation, ROTATIONS};
 
 pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Vec<Vec<Point3<i32>>>> {
     use combine::parser::char::*;
@@ -55,42 +39,125 @@ pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Vec<Vec<Point3<i32>>>>
     sep_by1(scanner(), newline()).skip(eof())
 }
 
+/// A rotation-invariant description of the displacement between two
+/// beacons: the absolute values of its coordinates, in order.
+type Shape = [i32; 3];
+
+type Pair = (Point3<i32>, Point3<i32>);
+
+fn shape(d: Vector3<i32>) -> Shape {
+    let mut shape = [d.x.abs(), d.y.abs(), d.z.abs()];
+    shape.sort_unstable();
+    shape
+}
+
 #[derive(PartialEq, Eq, Clone, Default, Debug)]
 struct ScannerFrame {
     beacons: HashSet<Point3<i32>>,
-    fingerprint: Counter<i32>,
+    /// Every pair of distinct beacons, in one order or the other,
+    /// indexed by the shape of the displacement between them.
+    pairs: HashMap<Shape, Vec<Pair>>,
 }
 
 impl ScannerFrame {
     fn from_distances(distances: Vec<Point3<i32>>) -> Self {
-        Self {
-            beacons: HashSet::from_iter(distances.iter().copied()),
-            fingerprint: Counter::from_iter(
-                distances
-                    .iter()
-                    .copied()
-                    .cartesian_product(distances.iter().copied())
-                    .map(|(u, v)| (u - v).cast::<f32>().norm_squared() as i32),
-            ),
+        let beacons = HashSet::<Point3<i32>>::from_iter(distances);
+        let mut pairs = HashMap::<_, Vec<_>>::new();
+        for (u, v) in beacons.iter().tuple_combinations() {
+            pairs.entry(shape(v - u)).or_default().push((*u, *v));
         }
+        Self { beacons, pairs }
     }
 
-    fn make_consistent(&self, other: &ScannerFrame, needed: usize) -> Option<IsometryMatrix3<f32>> {
-        if (self.fingerprint.clone() & other.fingerprint.clone()).len() <= needed * (needed - 1) / 2
+    /// Counts this frame's beacons that `iso` maps to within `window`
+    /// of one of the other frame's.
+    fn overlap(&self, other: &ScannerFrame, iso: Isometry, window: i32) -> usize {
+        if window == 0 {
+            self.beacons
+                .iter()
+                .filter(|u| other.beacons.contains(&(iso * **u)))
+                .count()
+        } else {
+            self.beacons
+                .iter()
+                .filter(|u| other.beacons.iter().any(|v| near(iso * **u, *v, window)))
+                .count()
+        }
+    }
+
+    /// Looks for an isometry that maps at least `params.overlap` of
+    /// this frame's beacons onto the other frame's, returning it along
+    /// with the number of beacons it matches up.
+    fn make_consistent(&self, other: &ScannerFrame, params: &Params) -> Option<(Isometry, usize)> {
+        // Jittered readings don't have matching shapes, and a single
+        // shared beacon doesn't make a pair.
+        if params.window() > 0 || params.overlap < 2 {
+            return self.make_consistent_brute(other, params);
+        }
+        // If enough beacons are shared, then so are the pairs of them.
+        let needed_pairs = params.overlap * (params.overlap - 1) / 2;
+        let common = self
+            .pairs
+            .iter()
+            .filter_map(|(shape, ps1)| other.pairs.get(shape).map(|ps2| (ps1, ps2)))
+            .collect_vec();
+        if common
+            .iter()
+            .map(|(ps1, ps2)| ps1.len().min(ps2.len()))
+            .sum::<usize>()
+            < needed_pairs
         {
             return None;
         }
+        // Each pair of pairs with the same shape votes for the
+        // isometries that line them up.  The right one gets a vote
+        // from each pair of shared beacons.
+        let mut votes = Counter::<Isometry>::new();
+        for (ps1, ps2) in common {
+            for ((a1, a2), &(b1, b2)) in ps1.iter().cartesian_product(ps2) {
+                for (b1, b2) in [(b1, b2), (b2, b1)] {
+                    for &rotation in ROTATIONS.iter().filter(|&&r| r * (a2 - a1) == b2 - b1) {
+                        votes[&Isometry {
+                            rotation,
+                            translation: b1 - rotation * *a1,
+                        }] += 1;
+                    }
+                }
+            }
+        }
+        let ranked = votes.most_common();
+        let (winner, n) = match ranked.first() {
+            Some(&(iso, n)) if n >= needed_pairs => (iso, n),
+            _ => return None,
+        };
+        // If the votes tie (say, because the shared beacons are laid
+        // out symmetrically), or the winner doesn't actually line up
+        // enough beacons, they don't settle it; check every alignment
+        // instead.
+        let tied = ranked.get(1).map_or(false, |&(_, m)| m == n);
+        let overlap = self.overlap(other, winner, 0);
+        if tied || overlap < params.overlap {
+            return self.make_consistent_brute(other, params);
+        }
+        Some((winner, overlap))
+    }
+
+    /// Like `make_consistent`, but tries lining up every pair of
+    /// beacons in every orientation.
+    fn make_consistent_brute(
+        &self,
+        other: &ScannerFrame,
+        params: &Params,
+    ) -> Option<(Isometry, usize)> {
         for (b1, b2) in self.beacons.iter().cartesian_product(other.beacons.iter()) {
-            for r in *ROTATIONS {
-                let t = Translation3::from(b2.cast() - r * b1.cast());
-                let iso = IsometryMatrix3::from_parts(t.cast(), r);
-                let beacons = self
-                    .beacons
-                    .iter()
-                    .map(|u| (iso * u.cast()).map(|n| n as i32))
-                    .collect::<HashSet<_>>();
-                if beacons.intersection(&other.beacons).count() >= needed {
-                    return Some(iso);
+            for &rotation in ROTATIONS.iter() {
+                let iso = Isometry {
+                    rotation,
+                    translation: b2 - rotation * *b1,
+                };
+                let overlap = self.overlap(other, iso, params.window());
+                if overlap >= params.overlap {
+                    return Some((iso, overlap));
                 }
             }
         }
@@ -98,10 +165,21 @@ impl ScannerFrame {
     }
 }
 
+/// Indicates whether two points are within `window` of each other
+/// along every axis.
+fn near(u: Point3<i32>, v: Point3<i32>, window: i32) -> bool {
+    (u - v).iter().all(|d| d.abs() <= window)
+}
+
 #[cfg(test)]
 mod test_make_consistent {
     use super::*;
 
+    const EXACT3: Params = Params {
+        overlap: 3,
+        tolerance: 0,
+    };
+
     lazy_static::lazy_static! {
         static ref FRAME1: ScannerFrame = ScannerFrame::from_distances(
             vec![
@@ -129,28 +207,98 @@ mod test_make_consistent {
 
     #[test]
     fn test_make_consistent_self() {
-        assert!(FRAME1.make_consistent(&FRAME1, 3).is_some());
-        assert!(FRAME2.make_consistent(&FRAME2, 3).is_some());
+        assert!(FRAME1.make_consistent(&FRAME1, &EXACT3).is_some());
+        assert!(FRAME2.make_consistent(&FRAME2, &EXACT3).is_some());
     }
 
     #[test]
     fn test_make_consistent_rot90() {
-        assert!(FRAME1.make_consistent(&FRAME2, 3).is_some());
-        assert!(FRAME2.make_consistent(&FRAME1, 3).is_some());
+        assert!(FRAME1.make_consistent(&FRAME2, &EXACT3).is_some());
+        assert!(FRAME2.make_consistent(&FRAME1, &EXACT3).is_some());
     }
 
     #[test]
     fn test_make_consistent_rot18() {
-        assert!(FRAME1.make_consistent(&FRAME3, 3).is_some());
-        assert!(FRAME3.make_consistent(&FRAME1, 3).is_some());
+        assert!(FRAME1.make_consistent(&FRAME3, &EXACT3).is_some());
+        assert!(FRAME3.make_consistent(&FRAME1, &EXACT3).is_some());
+    }
+
+    #[test]
+    fn test_make_consistent_symmetric() {
+        // A square is unchanged by quarter turns about its axis, so
+        // four isometries line it up equally well, and the votes tie.
+        let square = vec![
+            Point3::new(2, 1, 0),
+            Point3::new(-1, 2, 0),
+            Point3::new(-2, -1, 0),
+            Point3::new(1, -2, 0),
+        ];
+        let params = Params {
+            overlap: 4,
+            tolerance: 0,
+        };
+        let frame1 = ScannerFrame::from_distances(square.clone());
+        for &rotation in ROTATIONS.iter() {
+            let pose = Isometry {
+                rotation,
+                translation: Vector3::new(10, -20, 30),
+            };
+            let frame2 = ScannerFrame::from_distances(square.iter().map(|&b| pose * b).collect());
+            let found = frame1.make_consistent(&frame2, &params);
+            assert_eq!(found.map(|(_, overlap)| overlap), Some(4));
+            assert_eq!(found, frame1.make_consistent_brute(&frame2, &params));
+        }
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Solution {
     pub scanners: HashSet<Point3<i32>>,
     pub beacons: HashSet<Point3<i32>>,
+    /// The pose of each scanner relative to scanner 0, in input
+    /// order; it maps the scanner's own coordinates into scanner 0's.
+    pub poses: Vec<Isometry>,
+    /// The number of beacons shared by each pair of scanners `(i, j)`
+    /// with `i < j` whose views could be aligned.
+    pub overlaps: HashMap<(usize, usize), usize>,
+}
+
+/// Settings for matching up scanners.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Params {
+    /// The number of beacons two scanners must share before their
+    /// views are aligned.
+    pub overlap: usize,
+    /// The largest error, along each axis, in any scanner's reading
+    /// of a beacon's position.
+    pub tolerance: i32,
+}
+
+impl Params {
+    /// How far apart two readings of the same beacon can be once
+    /// their scanners are aligned: each reading can be off by the
+    /// tolerance, and so can the alignment, which is derived from a
+    /// pair of readings.
+    fn window(&self) -> i32 {
+        4 * self.tolerance
+    }
+}
+
+/// The puzzle's settings: 12 shared beacons, read exactly.
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            overlap: 12,
+            tolerance: 0,
+        }
+    }
 }
 
+/// The scanners, by index, that couldn't be aligned with scanner 0,
+/// even indirectly.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct UnlocatedScanners(pub Vec<usize>);
+
 #[derive(Eq, PartialEq, Clone, Default, Debug)]
 pub struct Problem {
     scanners: Vec<ScannerFrame>,
@@ -166,7 +314,16 @@ impl Problem {
         }
     }
 
-    pub fn solve(&self) -> Solution {
+    pub fn solve(&self) -> Result<Solution, UnlocatedScanners> {
+        self.solve_with(&Params::default())
+    }
+
+    /// Like `solve`, but with custom settings.
+    ///
+    /// With a nonzero tolerance, readings of a beacon from different
+    /// scanners are merged into the first one seen, so the beacon
+    /// positions are only as precise as the readings.
+    pub fn solve_with(&self, params: &Params) -> Result<Solution, UnlocatedScanners> {
         // Make a graph of scanner views, with edges given by
         // transformations that make views consistent.  Specifically,
         // if there is an edge t from s1 to s2, then t(s1) is
@@ -190,13 +347,31 @@ impl Problem {
             .filter(|((i, _), (j, _))| i < j)
             .collect_vec();
         pairs.par_iter().for_each(|((i, s1), (j, s2))| {
-            if let Some(t) = s1.make_consistent(s2, 12) {
+            if let Some((t, overlap)) = s1.make_consistent(s2, params) {
                 let mut graph = graph.lock().unwrap();
-                graph.add_edge(NodeIndex::new(*i), NodeIndex::new(*j), t);
-                graph.add_edge(NodeIndex::new(*j), NodeIndex::new(*i), t.inverse());
+                graph.add_edge(NodeIndex::new(*i), NodeIndex::new(*j), (t, overlap));
+                graph.add_edge(
+                    NodeIndex::new(*j),
+                    NodeIndex::new(*i),
+                    (t.inverse(), overlap),
+                );
             }
         });
         let graph = graph.into_inner().unwrap();
+        let overlaps = graph
+            .raw_edges()
+            .iter()
+            .map(|e| ((e.source().index(), e.target().index()), e.weight.1))
+            .filter(|((i, j), _)| i < j)
+            .collect::<HashMap<_, _>>();
+        if self.scanners.is_empty() {
+            return Ok(Solution {
+                scanners: HashSet::new(),
+                beacons: HashSet::new(),
+                poses: Vec::new(),
+                overlaps,
+            });
+        }
         let mut dfs = petgraph::visit::Dfs::new(&graph, NodeIndex::new(0));
         // Map from nodes to their parents.
         let mut parents = HashMap::new();
@@ -206,13 +381,14 @@ impl Problem {
         while let Some(node) = dfs.next(&graph) {
             let iso = match parents.get(&node) {
                 // Root node.
-                None => IsometryMatrix3::identity(),
+                None => Isometry::identity(),
                 // Child of some parent.
                 Some(parent) => {
                     isometries[parent]
                         * graph
                             .edge_weight(graph.find_edge(node, *parent).unwrap())
                             .unwrap()
+                            .0
                 }
             };
             for other in dfs.stack.iter() {
@@ -220,15 +396,154 @@ impl Problem {
                     parents.insert(*other, node);
                 }
             }
-            scanners.insert((iso * Point3::new(0_f32, 0_f32, 0_f32)).map(|n| n as i32));
-            beacons.extend(
-                self.scanners[node.index()]
-                    .beacons
-                    .iter()
-                    .map(|b| (iso * b.cast()).map(|n| n as i32)),
-            );
+            scanners.insert(iso * Point3::origin());
+            for b in self.scanners[node.index()].beacons.iter() {
+                let b = iso * *b;
+                let window = params.window();
+                if window == 0 || !beacons.iter().any(|v| near(b, *v, window)) {
+                    beacons.insert(b);
+                }
+            }
             isometries.insert(node, iso);
         }
-        Solution { scanners, beacons }
+        let unlocated = (0..self.scanners.len())
+            .filter(|i| !isometries.contains_key(&NodeIndex::new(*i)))
+            .collect_vec();
+        if !unlocated.is_empty() {
+            return Err(UnlocatedScanners(unlocated));
+        }
+        let poses = (0..self.scanners.len())
+            .map(|i| isometries[&NodeIndex::new(i)])
+            .collect_vec();
+        Ok(Solution {
+            scanners,
+            beacons,
+            poses,
+            overlaps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_solve {
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn beacons() -> Vec<Point3<i32>> {
+        (1..=14)
+            .map(|i| Point3::new(i * 37 % 101 - 50, i * i * 13 % 97 - 48, i * i * i % 89 - 44))
+            .collect()
+    }
+
+    fn pose() -> Isometry {
+        Isometry {
+            rotation: ROTATIONS[7],
+            translation: Vector3::new(68, -1246, -43),
+        }
+    }
+
+    #[test]
+    fn test_solve_poses() {
+        let view = beacons()
+            .into_iter()
+            .map(|b| pose().inverse() * b)
+            .collect();
+        let solution = Problem::from_input(vec![beacons(), view]).solve().unwrap();
+        assert_eq!(solution.poses, vec![Isometry::identity(), pose()]);
+        assert_eq!(solution.overlaps, HashMap::from([((0, 1), 14)]));
+        assert_eq!(
+            solution.scanners,
+            HashSet::from([Point3::origin(), Point3::new(68, -1246, -43)])
+        );
+        assert_eq!(solution.beacons, HashSet::from_iter(beacons()));
+    }
+
+    #[test]
+    fn test_solve_unlocated() {
+        let view = beacons()
+            .into_iter()
+            .map(|b| pose().inverse() * b)
+            .collect();
+        let stray = vec![Point3::new(1, 2, 3), Point3::new(4, 5, 6)];
+        let problem = Problem::from_input(vec![beacons(), stray.clone(), view, stray]);
+        assert_eq!(problem.solve().unwrap_err(), UnlocatedScanners(vec![1, 3]));
+    }
+
+    #[test]
+    fn test_solve_scenario_exact() {
+        let mut rng = SmallRng::seed_from_u64(19);
+        let scenario = scenario::generate(&mut rng, 5, 4, 6, 0);
+        let params = Params {
+            overlap: 4,
+            tolerance: 0,
+        };
+        let solution = Problem::from_input(scenario.readings)
+            .solve_with(&params)
+            .unwrap();
+        assert_eq!(solution.poses, scenario.poses);
+        assert_eq!(solution.beacons, HashSet::from_iter(scenario.beacons));
+        assert_eq!(solution.overlaps.len(), 4);
+        assert!(solution.overlaps.values().all(|&n| n == 4));
+    }
+
+    #[test]
+    fn test_solve_scenario_jitter() {
+        let mut rng = SmallRng::seed_from_u64(2021);
+        let scenario = scenario::generate(&mut rng, 4, 6, 4, 2);
+        let params = Params {
+            overlap: 6,
+            tolerance: 2,
+        };
+        let solution = Problem::from_input(scenario.readings)
+            .solve_with(&params)
+            .unwrap();
+        // Each alignment can be off by twice the tolerance, and the
+        // errors add up along the chain.
+        for (k, (pose, truth)) in solution.poses.iter().zip(&scenario.poses).enumerate() {
+            assert_eq!(pose.rotation, truth.rotation);
+            let error = pose.translation - truth.translation;
+            assert!(error.iter().all(|d| d.abs() <= 4 * k as i32));
+        }
+        assert_eq!(solution.beacons.len(), scenario.beacons.len());
+    }
+
+    #[test]
+    fn test_solve_scenario_too_few_shared() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let scenario = scenario::generate(&mut rng, 3, 3, 5, 0);
+        let problem = Problem::from_input(scenario.readings);
+        assert_eq!(problem.solve().unwrap_err(), UnlocatedScanners(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_make_consistent_matches_brute() {
+        let mut rng = SmallRng::seed_from_u64(41);
+        let scenario = scenario::generate(&mut rng, 4, 4, 4, 0);
+        let frames = scenario
+            .readings
+            .into_iter()
+            .map(ScannerFrame::from_distances)
+            .collect_vec();
+        let params = Params {
+            overlap: 4,
+            tolerance: 0,
+        };
+        for (s1, s2) in frames.iter().cartesian_product(&frames) {
+            assert_eq!(
+                s1.make_consistent(s2, &params),
+                s1.make_consistent_brute(s2, &params)
+            );
+        }
+    }
+
+    #[test]
+    fn test_solve_scenario_many_scanners() {
+        let mut rng = SmallRng::seed_from_u64(100);
+        let scenario = scenario::generate(&mut rng, 120, 12, 14, 0);
+        let solution = Problem::from_input(scenario.readings).solve().unwrap();
+        assert_eq!(solution.poses, scenario.poses);
+        assert_eq!(solution.beacons.len(), scenario.beacons.len());
     }
 }
@@ -3,7 +3,7 @@ use nalgebra::Point3;
 
 fn run(input: Vec<Vec<Point3<i32>>>) -> usize {
     let problem = Problem::from_input(input);
-    let solution = problem.solve();
+    let solution = problem.solve().unwrap();
     solution.beacons.len()
 }
 
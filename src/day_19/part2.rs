@@ -4,7 +4,7 @@ use nalgebra::Point3;
 
 fn run(input: Vec<Vec<Point3<i32>>>) -> i32 {
     let problem = Problem::from_input(input);
-    let solution = problem.solve();
+    let solution = problem.solve().unwrap();
     solution
         .scanners
         .iter()
@@ -0,0 +1,167 @@
+//! The 24 rotations of a cube, and the isometries built from them,
+//! with exact integer arithmetic.
+
+use std::ops::Mul;
+
+use itertools::Itertools;
+use nalgebra::{Matrix3, Point3, Vector3};
+
+lazy_static::lazy_static! {
+    /// Every rotation that maps the axes onto the axes, i.e., the
+    /// signed permutation matrices with determinant 1.
+    pub static ref ROTATIONS: [Rotation; 24] = itertools::iproduct!(
+        [1, -1].into_iter(),
+        [1, -1].into_iter(),
+        [1, -1].into_iter(),
+        [0, 1, 2].into_iter().permutations(3)
+    )
+    .map(|(sgn1, sgn2, sgn3, ixs)| {
+        let mut m = Matrix3::zeros();
+        for (i, (sgn, j)) in [sgn1, sgn2, sgn3].iter().copied().zip(ixs).enumerate() {
+            m[(i, j)] = sgn;
+        }
+        m
+    })
+    .filter(|m| determinant(m) == 1)
+    .map(Rotation)
+    .collect_vec()
+    .try_into()
+    .unwrap_or_else(|_| unreachable!());
+}
+
+fn determinant(m: &Matrix3<i32>) -> i32 {
+    m[(0, 0)] * (m[(1, 1)] * m[(2, 2)] - m[(1, 2)] * m[(2, 1)])
+        - m[(0, 1)] * (m[(1, 0)] * m[(2, 2)] - m[(1, 2)] * m[(2, 0)])
+        + m[(0, 2)] * (m[(1, 0)] * m[(2, 1)] - m[(1, 1)] * m[(2, 0)])
+}
+
+/// A rotation by multiples of 90 degrees, as a signed permutation
+/// matrix.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rotation(Matrix3<i32>);
+
+impl Rotation {
+    pub fn identity() -> Self {
+        Rotation(Matrix3::identity())
+    }
+
+    pub fn matrix(&self) -> &Matrix3<i32> {
+        &self.0
+    }
+
+    /// Returns the inverse rotation, which for a permutation matrix is
+    /// just the transpose.
+    pub fn inverse(&self) -> Self {
+        Rotation(self.0.transpose())
+    }
+}
+
+/// Composes two rotations, applying `rhs` first.
+impl Mul for Rotation {
+    type Output = Rotation;
+
+    fn mul(self, rhs: Rotation) -> Rotation {
+        Rotation(self.0 * rhs.0)
+    }
+}
+
+impl Mul<Vector3<i32>> for Rotation {
+    type Output = Vector3<i32>;
+
+    fn mul(self, rhs: Vector3<i32>) -> Vector3<i32> {
+        self.0 * rhs
+    }
+}
+
+impl Mul<Point3<i32>> for Rotation {
+    type Output = Point3<i32>;
+
+    fn mul(self, rhs: Point3<i32>) -> Point3<i32> {
+        Point3::from(self.0 * rhs.coords)
+    }
+}
+
+/// A rotation followed by a translation.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Isometry {
+    pub rotation: Rotation,
+    pub translation: Vector3<i32>,
+}
+
+impl Isometry {
+    pub fn identity() -> Self {
+        Isometry {
+            rotation: Rotation::identity(),
+            translation: Vector3::zeros(),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        let rotation = self.rotation.inverse();
+        Isometry {
+            rotation,
+            translation: -(rotation * self.translation),
+        }
+    }
+}
+
+/// Composes two isometries, applying `rhs` first.
+impl Mul for Isometry {
+    type Output = Isometry;
+
+    fn mul(self, rhs: Isometry) -> Isometry {
+        Isometry {
+            rotation: self.rotation * rhs.rotation,
+            translation: self.rotation * rhs.translation + self.translation,
+        }
+    }
+}
+
+impl Mul<Point3<i32>> for Isometry {
+    type Output = Point3<i32>;
+
+    fn mul(self, rhs: Point3<i32>) -> Point3<i32> {
+        self.rotation * rhs + self.translation
+    }
+}
+
+#[cfg(test)]
+mod test_rotation {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_rotations_distinct() {
+        let set = ROTATIONS.iter().copied().collect::<HashSet<_>>();
+        assert_eq!(set.len(), 24);
+        assert!(set.contains(&Rotation::identity()));
+    }
+
+    #[test]
+    fn test_rotations_closed() {
+        let set = ROTATIONS.iter().copied().collect::<HashSet<_>>();
+        for (&r1, &r2) in ROTATIONS.iter().cartesian_product(ROTATIONS.iter()) {
+            assert!(set.contains(&(r1 * r2)));
+        }
+        for &r in ROTATIONS.iter() {
+            assert!(set.contains(&r.inverse()));
+            assert_eq!(r * r.inverse(), Rotation::identity());
+            assert_eq!(determinant(r.matrix()), 1);
+        }
+    }
+
+    #[test]
+    fn test_isometry_inverse() {
+        let p = Point3::new(1, -20, 300);
+        for &rotation in ROTATIONS.iter() {
+            let iso = Isometry {
+                rotation,
+                translation: Vector3::new(-7, 11, 1_000_000),
+            };
+            assert_eq!(iso.inverse() * (iso * p), p);
+            assert_eq!(iso * iso.inverse(), Isometry::identity());
+            assert_eq!((iso * iso) * p, iso * (iso * p));
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Random scanner scenarios with known ground truth, for testing the
+//! solver on inputs other than the puzzle's.
+
+use nalgebra::{Point3, Vector3};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::{near, Isometry, ROTATIONS};
+
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    /// The true beacon positions, in scanner 0's frame.
+    pub beacons: Vec<Point3<i32>>,
+    /// The true pose of each scanner, as in `Solution::poses`.
+    pub poses: Vec<Isometry>,
+    /// What each scanner reports, in its own frame.
+    pub readings: Vec<Vec<Point3<i32>>>,
+}
+
+fn random_point<R: Rng>(rng: &mut R, center: Point3<i32>, radius: i32) -> Point3<i32> {
+    center + Vector3::from_fn(|_, _| rng.gen_range(-radius..=radius))
+}
+
+/// Generates a chain of scanners in which each one shares `shared`
+/// beacons with the one before it and sees `private` beacons of its
+/// own, with every reading off by up to `jitter` along each axis.
+pub fn generate<R: Rng>(
+    rng: &mut R,
+    scanners: usize,
+    shared: usize,
+    private: usize,
+    jitter: i32,
+) -> Scenario {
+    let mut poses = vec![Isometry::identity()];
+    while poses.len() < scanners {
+        poses.push(Isometry {
+            rotation: *ROTATIONS.choose(rng).unwrap_or_else(|| unreachable!()),
+            translation: random_point(rng, Point3::origin(), 2000).coords,
+        });
+    }
+
+    // Keep beacons far enough apart that jittered readings of
+    // different beacons can't be confused.
+    let mut beacons = Vec::<Point3<i32>>::new();
+    let mut place = |rng: &mut R, center: Point3<i32>| loop {
+        let b = random_point(rng, center, 1000);
+        if !beacons.iter().any(|v| near(b, *v, 16 * jitter + 1)) {
+            beacons.push(b);
+            break b;
+        }
+    };
+    let mut visible = vec![Vec::new(); scanners];
+    for (k, pose) in poses.iter().enumerate() {
+        let center = *pose * Point3::origin();
+        for _ in 0..private {
+            visible[k].push(place(rng, center));
+        }
+        if k > 0 {
+            for _ in 0..shared {
+                let b = place(rng, center);
+                visible[k - 1].push(b);
+                visible[k].push(b);
+            }
+        }
+    }
+
+    let readings = visible
+        .into_iter()
+        .zip(poses.iter())
+        .map(|(bs, pose)| {
+            bs.into_iter()
+                .map(|b| random_point(rng, pose.inverse() * b, jitter))
+                .collect()
+        })
+        .collect();
+    Scenario {
+        beacons,
+        poses,
+        readings,
+    }
+}
@@ -1,88 +1,48 @@
+mod packed;
 use combine::Parser;
 use itertools::Itertools;
 use ndarray::Array2;
 
+pub use self::packed::PackedImage;
+
+/// An image on an infinite grid: a finite window of pixels, and a
+/// background that every pixel outside the window shares.
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Image {
     pub pixels: Array2<bool>,
-    pub fill: Array2<bool>,
-    offset: isize,
+    pub background: bool,
 }
 
 impl Image {
     pub fn from_pixels(pixels: Array2<bool>) -> Self {
-        let fill = Array2::from_elem([3, 3], false);
-        let offset = 0;
         Self {
             pixels,
-            fill,
-            offset,
-        }
-    }
-
-    #[must_use]
-    pub fn enhance(&self, key: &[bool]) -> Self {
-        let (h, w) = self.pixels.dim();
-        let mut pixels = Array2::from_elem([h + 4, w + 4], false);
-        for (i, j) in (0..h + 4).cartesian_product(0..w + 4) {
-            Image::enhance_from(
-                |i, j| self.get_pixel(i - 2, j - 2),
-                &mut pixels,
-                key,
-                i as isize,
-                j as isize,
-            );
-        }
-        let mut fill = Array2::from_elem(self.fill.dim(), false);
-        for (i, j) in (0..3).cartesian_product(0..3) {
-            Image::enhance_from(
-                |i, j| self.get_fill_pixel(i, j),
-                &mut fill,
-                key,
-                i as isize,
-                j as isize,
-            );
-        }
-        let offset = (self.offset - 2) % 3;
-        Image {
-            pixels,
-            fill,
-            offset,
+            background: false,
         }
     }
 
-    fn get_fill_pixel(&self, i: isize, j: isize) -> bool {
-        let i = (i.rem_euclid(3)) as usize;
-        let j = (j.rem_euclid(3)) as usize;
-        self.fill[(i, j)]
+    /// Returns the pixel at `(i, j)`, counting from the top left of
+    /// the window.
+    pub fn get(&self, i: isize, j: isize) -> bool {
+        usize::try_from(i)
+            .ok()
+            .zip(usize::try_from(j).ok())
+            .and_then(|ix| self.pixels.get(ix).copied())
+            .unwrap_or(self.background)
     }
 
-    fn get_pixel(&self, i: isize, j: isize) -> bool {
-        let (h, w) = self.pixels.dim();
-        let h = h as isize;
-        let w = w as isize;
-        if i < 0 || i >= h || j < 0 || j >= w {
-            self.get_fill_pixel(i + self.offset, j + self.offset)
+    /// Counts the lit pixels, or returns `None` if there are
+    /// infinitely many.
+    pub fn lit(&self) -> Option<usize> {
+        if self.background {
+            None
         } else {
-            self.pixels[(i as usize, j as usize)]
+            Some(self.pixels.iter().filter(|x| **x).count())
         }
     }
 
-    fn enhance_from<F>(src: F, dst: &mut Array2<bool>, key: &[bool], i: isize, j: isize)
-    where
-        F: Fn(isize, isize) -> bool,
-    {
-        let n = [i + 1, i, i - 1]
-            .into_iter()
-            .cartesian_product([j + 1, j, j - 1].into_iter())
-            .enumerate()
-            .fold(0, |n, (k, (i, j))| n | ((src(i, j) as usize) << k));
-        let i = i as usize;
-        let j = j as usize;
-        dst[(i, j)] = key[n];
-    }
-
-    fn array_to_string(a: &Array2<bool>) -> String {
-        let iter = a.rows().into_iter().map(|row| {
+    pub fn pixels_to_string(&self) -> String {
+        let iter = self.pixels.rows().into_iter().map(|row| {
             row.iter()
                 .copied()
                 .map(|b| if b { '#' } else { '.' })
@@ -90,28 +50,122 @@ impl Image {
         });
         Itertools::intersperse(iter, "\n".to_string()).collect()
     }
+}
 
-    pub fn pixels_to_string(&self) -> String {
-        Image::array_to_string(&self.pixels)
+/// A cellular automaton in which each pixel's next state is looked up
+/// in a rule table, indexed by the square neighborhood of the given
+/// radius around it read as a binary number: row by row from the top
+/// left, most significant bit first.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Automaton {
+    rule: Vec<bool>,
+    radius: usize,
+}
+
+/// The rule table's length doesn't match the automaton's radius.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BadRuleLength {
+    /// The number of entries the radius calls for, if that fits in a
+    /// `usize`.
+    pub expected: Option<usize>,
+    pub found: usize,
+}
+
+impl Automaton {
+    pub fn new(rule: Vec<bool>, radius: usize) -> Result<Self, BadRuleLength> {
+        let expected = Self::rule_len(radius);
+        if expected == Some(rule.len()) {
+            Ok(Self { rule, radius })
+        } else {
+            Err(BadRuleLength {
+                expected,
+                found: rule.len(),
+            })
+        }
+    }
+
+    /// Builds an automaton from a function of the neighborhood, as an
+    /// index into the rule table.
+    pub fn from_fn<F>(radius: usize, f: F) -> Result<Self, BadRuleLength>
+    where
+        F: FnMut(usize) -> bool,
+    {
+        match Self::rule_len(radius) {
+            Some(len) => Self::new((0..len).map(f).collect(), radius),
+            None => Err(BadRuleLength {
+                expected: None,
+                found: 0,
+            }),
+        }
+    }
+
+    fn rule_len(radius: usize) -> Option<usize> {
+        let bits = u32::try_from(Self::side(radius).checked_pow(2)?).ok()?;
+        1_usize.checked_shl(bits)
+    }
+
+    fn side(radius: usize) -> usize {
+        2 * radius + 1
+    }
+
+    pub fn radius(&self) -> usize {
+        self.radius
     }
 
-    pub fn fill_to_string(&self) -> String {
-        Image::array_to_string(&self.fill)
+    /// The number of pixels in a neighborhood, i.e., the number of
+    /// bits in an index into the rule table.
+    pub fn key_bits(&self) -> usize {
+        Self::side(self.radius).pow(2)
+    }
+
+    /// The background after a step: either all of a neighborhood is
+    /// lit, or none of it is.
+    fn next_background(&self, background: bool) -> bool {
+        if background {
+            self.rule[self.rule.len() - 1]
+        } else {
+            self.rule[0]
+        }
+    }
+
+    /// Runs the automaton for one step.  The window grows by the
+    /// radius on each side; anything further out only sees the
+    /// background.
+    #[must_use]
+    pub fn step(&self, image: &Image) -> Image {
+        let r = self.radius as isize;
+        let (h, w) = image.pixels.dim();
+        let pad = 2 * self.radius;
+        let pixels = Array2::from_shape_fn((h + pad, w + pad), |(i, j)| {
+            let (i, j) = (i as isize - r, j as isize - r);
+            let n = itertools::iproduct!(-r..=r, -r..=r)
+                .fold(0, |n, (di, dj)| n << 1 | image.get(i + di, j + dj) as usize);
+            self.rule[n]
+        });
+        let background = self.next_background(image.background);
+        Image { pixels, background }
+    }
+
+    /// Runs the automaton for `steps` steps, returning the final image
+    /// and the number of lit pixels after each step.
+    pub fn run(&self, image: &Image, steps: usize) -> (Image, Vec<Option<usize>>) {
+        let mut image = image.clone();
+        let mut lit = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            image = self.step(&image);
+            lit.push(image.lit());
+        }
+        (image, lit)
     }
 }
 
 pub struct Input {
-    pub key: Vec<bool>,
+    pub automaton: Automaton,
     pub image: Image,
 }
 
-impl Input {
-    pub fn enhance(&mut self) {
-        self.image = self.image.enhance(&self.key);
-    }
-}
-
 pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
+    use combine::error::StringStreamError;
     use combine::parser::char::*;
     use combine::*;
 
@@ -123,8 +177,10 @@ pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
         )
     }
 
-    fn key<'a>() -> impl Parser<&'a str, Output = Vec<bool>> {
-        many1(bool()).skip(newline())
+    fn key<'a>() -> impl Parser<&'a str, Output = Automaton> {
+        many1(bool())
+            .skip(newline())
+            .flat_map(|key| Automaton::new(key, 1).map_err(|_| StringStreamError::UnexpectedParse))
     }
 
     fn image<'a>() -> impl Parser<&'a str, Output = Image> {
@@ -140,5 +196,102 @@ pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
         .skip(newline())
         .and(image())
         .skip(eof())
-        .map(|(key, image)| Input { key, image })
+        .map(|(automaton, image)| Input { automaton, image })
+}
+
+#[cfg(test)]
+mod test_automaton {
+    use ndarray::array;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    /// The bit for the middle of a radius-1 neighborhood.
+    const CENTER: usize = 1 << 4;
+
+    /// Conway's Game of Life, as a radius-1 automaton.
+    fn life() -> Automaton {
+        Automaton::from_fn(1, |n| {
+            let alive = n & CENTER != 0;
+            let neighbors = (n & !CENTER).count_ones();
+            neighbors == 3 || (alive && neighbors == 2)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_automaton_blinker() {
+        let image = Image::from_pixels(array![
+            [false, true, false],
+            [false, true, false],
+            [false, true, false]
+        ]);
+        let (after, lit) = life().run(&image, 4);
+        assert_eq!(lit, vec![Some(3); 4]);
+        assert_eq!(after.pixels.dim(), (11, 11));
+        // Back to vertical, with the window grown by 4 on each side.
+        assert!(after.get(4, 5) && after.get(5, 5) && after.get(6, 5));
+        assert!(!after.get(5, 4) && !after.get(5, 6));
+        assert!(!after.get(-3, 100));
+    }
+
+    #[test]
+    fn test_automaton_background() {
+        // Invert everything, including the background.
+        let invert = Automaton::from_fn(1, |n| n & CENTER == 0).unwrap();
+        let image = Image::from_pixels(array![[true, false]]);
+        let (after, lit) = invert.run(&image, 3);
+        assert_eq!(lit, vec![None, Some(1), None]);
+        assert!(after.background);
+        assert!(!after.get(3, 3));
+        assert!(after.get(3, 4));
+    }
+
+    #[test]
+    fn test_automaton_radius() {
+        // Light up anything within two steps of a lit pixel.
+        let grow = Automaton::from_fn(2, |n| n != 0).unwrap();
+        assert_eq!(grow.key_bits(), 25);
+        let image = Image::from_pixels(array![[true]]);
+        let (after, lit) = grow.run(&image, 2);
+        assert_eq!(lit, vec![Some(25), Some(81)]);
+        assert_eq!(after.pixels.dim(), (9, 9));
+        assert_eq!(
+            Automaton::new(vec![false; 512], 2),
+            Err(BadRuleLength {
+                expected: Some(1 << 25),
+                found: 512,
+            })
+        );
+        assert_eq!(Automaton::from_fn(4, |_| false).unwrap_err().expected, None);
+    }
+
+    #[test]
+    fn test_packed_matches_image() {
+        let mut rng = SmallRng::seed_from_u64(20);
+        for (radius, seed) in itertools::iproduct!([0, 1, 2], 0..4_u64) {
+            // A cheap hash, so that radius 2 doesn't need a random
+            // table with 2^25 entries.
+            let salt = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            let automaton = Automaton::from_fn(radius, |n| {
+                (n as u64 ^ salt).wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40 & 1 != 0
+            })
+            .unwrap();
+            // Wide enough to span several words per row.
+            let shape = (rng.gen_range(1..10), rng.gen_range(1..200));
+            let image = Image {
+                pixels: Array2::from_shape_simple_fn(shape, || rng.gen()),
+                background: rng.gen(),
+            };
+            let (expected, expected_lit) = automaton.run(&image, 6);
+            let (packed, lit) = automaton.run_packed(&PackedImage::from(&image), 6);
+            assert_eq!(Image::from(&packed), expected);
+            assert_eq!(lit, expected_lit);
+            assert_eq!(
+                automaton.step_packed(&packed),
+                automaton.par_step_packed(&packed)
+            );
+        }
+    }
 }
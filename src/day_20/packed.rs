@@ -0,0 +1,195 @@
+//! A bit-packed image, for running automata for many steps.
+
+use itertools::Itertools;
+use ndarray::Array2;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use super::{Automaton, Image};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// The same as an `Image`, but with each row of the window packed into
+/// words, the leftmost pixel in the lowest bit.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PackedImage {
+    height: usize,
+    width: usize,
+    /// Row after row, each `words_per_row` long, with any bits past
+    /// the width kept clear.
+    words: Vec<u64>,
+    pub background: bool,
+}
+
+impl PackedImage {
+    fn words_per_row(width: usize) -> usize {
+        (width + WORD_BITS - 1) / WORD_BITS
+    }
+
+    pub fn dim(&self) -> (usize, usize) {
+        (self.height, self.width)
+    }
+
+    /// Returns the pixel at `(i, j)`, counting from the top left of
+    /// the window.
+    pub fn get(&self, i: isize, j: isize) -> bool {
+        match (usize::try_from(i), usize::try_from(j)) {
+            (Ok(i), Ok(j)) if i < self.height && j < self.width => {
+                let word = self.words[i * Self::words_per_row(self.width) + j / WORD_BITS];
+                word >> (j % WORD_BITS) & 1 != 0
+            }
+            _ => self.background,
+        }
+    }
+
+    /// Returns 128 pixels of row `i`, starting at column `j`, with the
+    /// leftmost in the lowest bit.
+    fn bits_from(&self, i: isize, j: isize) -> u128 {
+        let words_per_row = Self::words_per_row(self.width);
+        let background = if self.background { u64::MAX } else { 0 };
+        let row = usize::try_from(i)
+            .ok()
+            .filter(|&i| i < self.height)
+            .map(|i| &self.words[i * words_per_row..(i + 1) * words_per_row]);
+        let word = |q: isize| match (row, usize::try_from(q)) {
+            (Some(row), Ok(q)) if q < words_per_row => {
+                // Bits past the width are clear, but they stand for
+                // background pixels.
+                let valid = self.width - q * WORD_BITS;
+                if valid < WORD_BITS {
+                    row[q] | background << valid
+                } else {
+                    row[q]
+                }
+            }
+            _ => background,
+        };
+        let (q, offset) = (
+            j.div_euclid(WORD_BITS as isize),
+            j.rem_euclid(WORD_BITS as isize) as u32,
+        );
+        let lo = word(q) as u128 | (word(q + 1) as u128) << WORD_BITS;
+        match offset {
+            0 => lo,
+            _ => lo >> offset | (word(q + 2) as u128) << (2 * WORD_BITS as u32 - offset),
+        }
+    }
+
+    /// Counts the lit pixels, or returns `None` if there are
+    /// infinitely many.
+    pub fn lit(&self) -> Option<usize> {
+        if self.background {
+            None
+        } else {
+            Some(self.words.iter().map(|w| w.count_ones() as usize).sum())
+        }
+    }
+}
+
+impl From<&Image> for PackedImage {
+    fn from(image: &Image) -> Self {
+        let (height, width) = image.pixels.dim();
+        let words_per_row = Self::words_per_row(width);
+        let mut words = vec![0; height * words_per_row];
+        for ((i, j), _) in image.pixels.indexed_iter().filter(|(_, x)| **x) {
+            words[i * words_per_row + j / WORD_BITS] |= 1 << (j % WORD_BITS);
+        }
+        Self {
+            height,
+            width,
+            words,
+            background: image.background,
+        }
+    }
+}
+
+impl From<&PackedImage> for Image {
+    fn from(image: &PackedImage) -> Self {
+        Image {
+            pixels: Array2::from_shape_fn(image.dim(), |(i, j)| image.get(i as isize, j as isize)),
+            background: image.background,
+        }
+    }
+}
+
+impl Automaton {
+    /// Computes row `i` of the next step's window.
+    ///
+    /// Rather than gathering each pixel's neighborhood one pixel at a
+    /// time, this lines up the source rows for a whole word of output
+    /// at once, and then masks out each pixel's part of every row.
+    fn packed_row(&self, image: &PackedImage, i: usize) -> Vec<u64> {
+        let side = 2 * self.radius + 1;
+        let mask = (1 << side) - 1;
+        // The masked bits have the leftmost pixel lowest, but the rule
+        // table wants it highest.
+        let reversed = (0..=mask)
+            .map(|m: usize| m.reverse_bits() >> (usize::BITS as usize - side))
+            .collect_vec();
+        let width = image.width + 2 * self.radius;
+        // The output pixel `(i, j)` is centered on the input pixel
+        // `(i - r, j - r)`, so its neighborhood spans input rows
+        // `i - 2r..=i` and columns `j - 2r..=j`.
+        let top = i as isize - 2 * self.radius as isize;
+        let mut row = vec![0; PackedImage::words_per_row(width)];
+        for (w, word) in row.iter_mut().enumerate() {
+            let left = (w * WORD_BITS) as isize - 2 * self.radius as isize;
+            let sources = (0..side)
+                .map(|k| image.bits_from(top + k as isize, left))
+                .collect_vec();
+            for b in 0..WORD_BITS.min(width - w * WORD_BITS) {
+                let n = sources
+                    .iter()
+                    .fold(0, |n, s| n << side | reversed[(s >> b) as usize & mask]);
+                if self.rule[n] {
+                    *word |= 1 << b;
+                }
+            }
+        }
+        row
+    }
+
+    /// Like `step`, but on a packed image.
+    #[must_use]
+    pub fn step_packed(&self, image: &PackedImage) -> PackedImage {
+        let height = image.height + 2 * self.radius;
+        PackedImage {
+            height,
+            width: image.width + 2 * self.radius,
+            words: (0..height)
+                .flat_map(|i| self.packed_row(image, i))
+                .collect(),
+            background: self.next_background(image.background),
+        }
+    }
+
+    /// Like `step_packed`, but computes the rows in parallel.
+    #[must_use]
+    pub fn par_step_packed(&self, image: &PackedImage) -> PackedImage {
+        let height = image.height + 2 * self.radius;
+        let rows = (0..height)
+            .into_par_iter()
+            .map(|i| self.packed_row(image, i))
+            .collect::<Vec<_>>();
+        PackedImage {
+            height,
+            width: image.width + 2 * self.radius,
+            words: rows.concat(),
+            background: self.next_background(image.background),
+        }
+    }
+
+    /// Like `run`, but on a packed image, computing rows in parallel.
+    pub fn run_packed(
+        &self,
+        image: &PackedImage,
+        steps: usize,
+    ) -> (PackedImage, Vec<Option<usize>>) {
+        let mut image = image.clone();
+        let mut lit = Vec::with_capacity(steps);
+        for _ in 0..steps {
+            image = self.par_step_packed(&image);
+            lit.push(image.lit());
+        }
+        (image, lit)
+    }
+}
@@ -1,9 +1,10 @@
-use aoc2021::day_20::Input;
+use aoc2021::day_20::{Input, PackedImage};
 
-fn run(mut input: Input) -> usize {
-    input.enhance();
-    input.enhance();
-    input.image.pixels.iter().copied().filter(|x| *x).count()
+fn run(input: Input) -> usize {
+    let (_, lit) = input
+        .automaton
+        .run_packed(&PackedImage::from(&input.image), 2);
+    lit[1].unwrap()
 }
 
 aoc2021::make_main_combine!(20, aoc2021::day_20::parse_input, run);
@@ -1,4 +1,8 @@
+use std::collections::HashMap;
+
 use combine::Parser;
+use itertools::Itertools;
+use ndarray::Array2;
 
 pub struct Input {
     pub positions: [u32; 2],
@@ -22,15 +26,436 @@ pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
         })
 }
 
-pub struct Player {
-    pub position: u32,
-    pub score: u32,
+/// The parameters of a game of Dirac Dice.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rules {
+    /// The number of spaces on the circular track, numbered from 1.
+    pub board_size: u32,
+    /// The score that wins the game.
+    pub target: u32,
+    pub die_sides: u32,
+    /// The number of times the die is rolled on each turn; the player
+    /// moves forward by the total.
+    pub rolls_per_turn: u32,
+    pub players: usize,
+}
+
+/// Reasons a game can't start from a given state.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StartError {
+    /// The number of positions or scores doesn't match the number of
+    /// players, or there are no players at all.
+    PlayerCount(usize),
+    /// A position isn't on the board.
+    Position(u32),
+    /// The player to move doesn't exist.
+    Turn(usize),
+    /// Someone has already reached the target score.
+    Finished,
+}
+
+/// A point partway through a game.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GameState {
+    pub positions: Vec<u32>,
+    pub scores: Vec<u32>,
+    pub to_move: usize,
+}
+
+impl GameState {
+    /// The state before anyone has moved.
+    pub fn start(positions: &[u32]) -> Self {
+        Self {
+            positions: positions.to_vec(),
+            scores: vec![0; positions.len()],
+            to_move: 0,
+        }
+    }
+}
+
+/// The universes that branch off from some state of a quantum game.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Analysis {
+    /// The number of universes in which each player wins on each
+    /// turn, counting the next turn as turn 1.
+    pub wins_by_length: Vec<Vec<u128>>,
+    /// The number of universes each turn splits into.
+    branching: f64,
+}
+
+impl Analysis {
+    /// The number of universes in which each player wins.
+    pub fn wins(&self) -> Vec<u128> {
+        self.wins_by_length
+            .iter()
+            .map(|by_length| by_length.iter().sum())
+            .collect()
+    }
+
+    /// The number of universes in which the game ends on each turn,
+    /// counting the next turn as turn 1.
+    pub fn lengths(&self) -> Vec<u128> {
+        let longest = self.wins_by_length.iter().map(Vec::len).max().unwrap_or(0);
+        (0..longest)
+            .map(|k| {
+                self.wins_by_length
+                    .iter()
+                    .filter_map(|by_length| by_length.get(k))
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// The probability that each player wins.  Games that end sooner
+    /// split into fewer universes, so each of their universes is
+    /// more likely than one from a longer game.
+    pub fn win_probabilities(&self) -> Vec<f64> {
+        self.wins_by_length
+            .iter()
+            .map(|by_length| {
+                by_length
+                    .iter()
+                    .enumerate()
+                    .map(|(k, n)| *n as f64 / self.branching.powi(k as i32))
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+/// The end of a game with a deterministic die.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Outcome {
+    pub winner: usize,
+    pub scores: Vec<u32>,
+    /// The number of times the die was rolled.
+    pub rolls: u32,
+}
+
+impl Rules {
+    /// The rules for part 1.
+    pub const DETERMINISTIC: Rules = Rules {
+        board_size: 10,
+        target: 1000,
+        die_sides: 100,
+        rolls_per_turn: 3,
+        players: 2,
+    };
+
+    /// The rules for part 2.
+    pub const QUANTUM: Rules = Rules {
+        board_size: 10,
+        target: 21,
+        die_sides: 3,
+        rolls_per_turn: 3,
+        players: 2,
+    };
+
+    fn check(&self, positions: &[u32]) -> Result<(), StartError> {
+        if positions.is_empty() || positions.len() != self.players {
+            return Err(StartError::PlayerCount(positions.len()));
+        }
+        match positions
+            .iter()
+            .find(|p| !(1..=self.board_size).contains(*p))
+        {
+            Some(p) => Err(StartError::Position(*p)),
+            None => Ok(()),
+        }
+    }
+
+    fn check_state(&self, state: &GameState) -> Result<(), StartError> {
+        self.check(&state.positions)?;
+        if state.scores.len() != self.players {
+            Err(StartError::PlayerCount(state.scores.len()))
+        } else if state.to_move >= self.players {
+            Err(StartError::Turn(state.to_move))
+        } else if state.scores.iter().any(|s| *s >= self.target) {
+            Err(StartError::Finished)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the position `n` spaces after `position`.
+    pub fn advance(&self, position: u32, n: u32) -> u32 {
+        (((position - 1) + n) % self.board_size) + 1
+    }
+
+    /// Plays with a die that rolls 1, 2, and so on up to the number of
+    /// sides, and then starts over.
+    pub fn play_deterministic(&self, positions: &[u32]) -> Result<Outcome, StartError> {
+        self.check(positions)?;
+        let mut positions = positions.to_vec();
+        let mut scores = vec![0; self.players];
+        let mut die = (1..=self.die_sides).cycle();
+        let mut rolls = 0;
+        for player in (0..self.players).cycle() {
+            let total = die.by_ref().take(self.rolls_per_turn as usize).sum();
+            rolls += self.rolls_per_turn;
+            positions[player] = self.advance(positions[player], total);
+            scores[player] += positions[player];
+            if scores[player] >= self.target {
+                return Ok(Outcome {
+                    winner: player,
+                    scores,
+                    rolls,
+                });
+            }
+        }
+        // The cycle never ends, and `check` made sure it isn't empty.
+        unreachable!()
+    }
+
+    /// Plays with a die that splits the universe on each roll, once for
+    /// each side, and counts the universes in which each player wins.
+    pub fn play_quantum(&self, positions: &[u32]) -> Result<Vec<u128>, StartError> {
+        Ok(self.analyze(&GameState::start(positions))?.wins())
+    }
+
+    /// Plays a quantum game from the given state, keeping track of
+    /// when each universe's game ends.
+    pub fn analyze(&self, state: &GameState) -> Result<Analysis, StartError> {
+        self.check_state(state)?;
+        Ok(Memo::new(self).analyze(state))
+    }
+
+    /// For a two-player quantum game, tabulates the probability that
+    /// the first player wins for each pair of starting positions,
+    /// indexed by the positions minus 1.
+    pub fn first_seat_odds(&self) -> Result<Array2<f64>, StartError> {
+        if self.players != 2 {
+            return Err(StartError::PlayerCount(self.players));
+        }
+        let n = self.board_size as usize;
+        let mut memo = Memo::new(self);
+        Ok(Array2::from_shape_fn((n, n), |(i, j)| {
+            let state = GameState::start(&[i as u32 + 1, j as u32 + 1]);
+            memo.analyze(&state).win_probabilities()[0]
+        }))
+    }
+
+    /// The number of ways to roll each total in a turn.
+    fn totals(&self) -> Vec<(u32, u128)> {
+        let mut counts = HashMap::from([(0, 1)]);
+        for _ in 0..self.rolls_per_turn {
+            let mut next = HashMap::new();
+            for ((total, count), side) in counts.into_iter().cartesian_product(1..=self.die_sides) {
+                *next.entry(total + side).or_insert(0) += count;
+            }
+            counts = next;
+        }
+        counts.into_iter().collect()
+    }
+}
+
+/// Each player's position and score, and whose turn it is.
+type State = (Vec<(u32, u32)>, usize);
+
+/// Universe counts for quantum games, by state, in the form of
+/// `Analysis::wins_by_length`.
+struct Memo<'a> {
+    rules: &'a Rules,
+    totals: Vec<(u32, u128)>,
+    inner: HashMap<State, Vec<Vec<u128>>>,
+}
+
+impl<'a> Memo<'a> {
+    fn new(rules: &'a Rules) -> Self {
+        Self {
+            rules,
+            totals: rules.totals(),
+            inner: HashMap::new(),
+        }
+    }
+
+    fn analyze(&mut self, state: &GameState) -> Analysis {
+        let players = state
+            .positions
+            .iter()
+            .copied()
+            .zip(state.scores.iter().copied())
+            .collect_vec();
+        Analysis {
+            wins_by_length: self.get(players, state.to_move),
+            branching: (self.rules.die_sides as f64).powi(self.rules.rolls_per_turn as i32),
+        }
+    }
+
+    fn get(&mut self, players: Vec<(u32, u32)>, turn: usize) -> Vec<Vec<u128>> {
+        let key = (players, turn);
+        if let Some(wins) = self.inner.get(&key) {
+            return wins.clone();
+        }
+        let (players, turn) = key;
+        let mut wins = vec![Vec::new(); players.len()];
+        let mut add = |player: usize, length: usize, count: u128| {
+            let by_length: &mut Vec<u128> = &mut wins[player];
+            if by_length.len() <= length {
+                by_length.resize(length + 1, 0);
+            }
+            by_length[length] += count;
+        };
+        for (total, count) in self.totals.clone() {
+            let mut players = players.clone();
+            let (position, score) = &mut players[turn];
+            *position = self.rules.advance(*position, total);
+            *score += *position;
+            if *score >= self.rules.target {
+                add(turn, 1, count);
+            } else {
+                let next = self.get(players, (turn + 1) % self.rules.players);
+                for (player, by_length) in next.into_iter().enumerate() {
+                    for (length, n) in by_length.into_iter().enumerate() {
+                        add(player, length + 1, count * n);
+                    }
+                }
+            }
+        }
+        self.inner.insert((players, turn), wins.clone());
+        wins
+    }
 }
 
-impl Player {
-    pub fn advance(&mut self, n: u32) -> bool {
-        self.position = (((self.position - 1) + n) % 10) + 1;
-        self.score += self.position;
-        self.score >= 1000
+#[cfg(test)]
+mod test_rules {
+    use super::*;
+
+    #[test]
+    fn test_rules_example() {
+        let outcome = Rules::DETERMINISTIC.play_deterministic(&[4, 8]).unwrap();
+        assert_eq!(
+            outcome,
+            Outcome {
+                winner: 0,
+                scores: vec![1000, 745],
+                rolls: 993,
+            }
+        );
+        assert_eq!(
+            Rules::QUANTUM.play_quantum(&[4, 8]),
+            Ok(vec![444356092776315, 341960390180808])
+        );
+    }
+
+    #[test]
+    fn test_rules_one_sided_die() {
+        // A quantum game with a one-sided die has only one universe,
+        // which plays out like the deterministic game.
+        let rules = Rules {
+            board_size: 7,
+            target: 30,
+            die_sides: 1,
+            rolls_per_turn: 2,
+            players: 3,
+        };
+        let outcome = rules.play_deterministic(&[1, 4, 6]).unwrap();
+        let mut wins = vec![0; 3];
+        wins[outcome.winner] = 1;
+        assert_eq!(rules.play_quantum(&[1, 4, 6]), Ok(wins));
+    }
+
+    #[test]
+    fn test_rules_errors() {
+        let rules = Rules::QUANTUM;
+        assert_eq!(rules.play_quantum(&[1]), Err(StartError::PlayerCount(1)));
+        assert_eq!(
+            rules.play_deterministic(&[1, 11]),
+            Err(StartError::Position(11))
+        );
+        let rules = Rules {
+            players: 0,
+            ..rules
+        };
+        assert_eq!(
+            rules.play_deterministic(&[]),
+            Err(StartError::PlayerCount(0))
+        );
+    }
+
+    #[test]
+    fn test_analyze_example() {
+        let analysis = Rules::QUANTUM.analyze(&GameState::start(&[4, 8])).unwrap();
+        assert_eq!(analysis.wins(), vec![444356092776315, 341960390180808]);
+        assert_eq!(
+            analysis.lengths().iter().sum::<u128>(),
+            444356092776315 + 341960390180808
+        );
+        let odds = analysis.win_probabilities();
+        assert!((odds.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_analyze_midgame() {
+        // Any move wins, since positions are at least 1.
+        let state = GameState {
+            positions: vec![3, 5],
+            scores: vec![20, 0],
+            to_move: 0,
+        };
+        let analysis = Rules::QUANTUM.analyze(&state).unwrap();
+        assert_eq!(analysis.wins_by_length, vec![vec![0, 27], vec![]]);
+        assert_eq!(analysis.lengths(), vec![0, 27]);
+        assert_eq!(analysis.win_probabilities(), vec![1.0, 0.0]);
+
+        let state = GameState {
+            positions: vec![3, 5],
+            scores: vec![0, 20],
+            to_move: 1,
+        };
+        let analysis = Rules::QUANTUM.analyze(&state).unwrap();
+        assert_eq!(analysis.wins(), vec![0, 27]);
+    }
+
+    #[test]
+    fn test_analyze_one_sided_die() {
+        let rules = Rules {
+            board_size: 7,
+            target: 30,
+            die_sides: 1,
+            rolls_per_turn: 2,
+            players: 3,
+        };
+        let outcome = rules.play_deterministic(&[1, 4, 6]).unwrap();
+        let analysis = rules.analyze(&GameState::start(&[1, 4, 6])).unwrap();
+        let turns = (outcome.rolls / rules.rolls_per_turn) as usize;
+        assert_eq!(analysis.lengths().len(), turns + 1);
+        assert_eq!(analysis.lengths()[turns], 1);
+    }
+
+    #[test]
+    fn test_first_seat_odds() {
+        let odds = Rules::QUANTUM.first_seat_odds().unwrap();
+        assert_eq!(odds.dim(), (10, 10));
+        let analysis = Rules::QUANTUM.analyze(&GameState::start(&[4, 8])).unwrap();
+        assert_eq!(odds[(3, 7)], analysis.win_probabilities()[0]);
+        // Going first is an advantage when both start in the same
+        // place.
+        assert!(odds.diag().iter().all(|p| *p > 0.5));
+        let rules = Rules {
+            players: 3,
+            ..Rules::QUANTUM
+        };
+        assert_eq!(rules.first_seat_odds(), Err(StartError::PlayerCount(3)));
+    }
+
+    #[test]
+    fn test_analyze_errors() {
+        let rules = Rules::QUANTUM;
+        let state = GameState {
+            to_move: 2,
+            ..GameState::start(&[1, 2])
+        };
+        assert_eq!(rules.analyze(&state), Err(StartError::Turn(2)));
+        let state = GameState {
+            scores: vec![21, 0],
+            ..GameState::start(&[1, 2])
+        };
+        assert_eq!(rules.analyze(&state), Err(StartError::Finished));
+        let state = GameState {
+            scores: vec![0],
+            ..GameState::start(&[1, 2])
+        };
+        assert_eq!(rules.analyze(&state), Err(StartError::PlayerCount(1)));
     }
 }
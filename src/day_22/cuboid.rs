@@ -1,13 +1,21 @@
-use super::vector::Vector;
+use num_traits::Num;
 
+use crate::vector::Vector;
+
+/// The coordinates cuboids can have.
+pub trait Scalar: Copy + Ord + Num + Send + Sync {}
+
+impl<T: Copy + Ord + Num + Send + Sync> Scalar for T {}
+
+/// The points `p` with `lo <= p < hi`, componentwise.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub struct Cuboid {
-    lo: Vector<i64, 3>,
-    hi: Vector<i64, 3>,
+pub struct Cuboid<T = i64, const N: usize = 3> {
+    lo: Vector<T, N>,
+    hi: Vector<T, N>,
 }
 
-impl Cuboid {
-    pub fn new(lo: Vector<i64, 3>, hi: Vector<i64, 3>) -> Option<Self> {
+impl<T: Scalar, const N: usize> Cuboid<T, N> {
+    pub fn new(lo: Vector<T, N>, hi: Vector<T, N>) -> Option<Self> {
         if lo.iter().zip(hi.iter()).any(|(l, u)| l > u) {
             None
         } else {
@@ -15,8 +23,29 @@ impl Cuboid {
         }
     }
 
-    pub fn volume(self) -> i64 {
-        (self.hi - self.lo).iter().product()
+    pub fn lo(self) -> Vector<T, N> {
+        self.lo
+    }
+
+    pub fn hi(self) -> Vector<T, N> {
+        self.hi
+    }
+
+    /// The lower and upper bounds along `axis`.
+    pub fn extent(self, axis: usize) -> (T, T) {
+        (self.lo[axis], self.hi[axis])
+    }
+
+    pub fn volume(self) -> T {
+        (self.hi - self.lo).into_iter().fold(T::one(), |v, d| v * d)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.lo.iter().zip(self.hi.iter()).any(|(l, u)| l == u)
+    }
+
+    pub fn contains(self, point: Vector<T, N>) -> bool {
+        (0..N).all(|i| self.lo[i] <= point[i] && point[i] < self.hi[i])
     }
 
     pub fn intersect(self, other: Self) -> Option<Self> {
@@ -31,4 +60,221 @@ impl Cuboid {
             Some(i) => i == self,
         }
     }
+
+    /// Splits off the parts of this cuboid outside `other`, as at most
+    /// `2 * N` disjoint, nonempty cuboids.
+    pub fn difference(self, other: Self) -> Vec<Self> {
+        let common = match self.intersect(other) {
+            Some(c) if !c.is_empty() => c,
+            _ => return vec![self],
+        };
+        // Slice off the slabs on either side of `common`, one axis at
+        // a time, until only `common` is left.
+        let mut pieces = Vec::new();
+        let mut rest = self;
+        for i in 0..N {
+            if rest.lo[i] < common.lo[i] {
+                let mut below = rest;
+                below.hi[i] = common.lo[i];
+                pieces.push(below);
+                rest.lo[i] = common.lo[i];
+            }
+            if common.hi[i] < rest.hi[i] {
+                let mut above = rest;
+                above.lo[i] = common.hi[i];
+                pieces.push(above);
+                rest.hi[i] = common.hi[i];
+            }
+        }
+        pieces
+    }
+}
+
+/// A set of points, as a list of disjoint, nonempty cuboids.
+///
+/// Overlaps are removed by splitting cuboids apart, so the list only
+/// grows with the number of pieces actually in the set.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CuboidSet<T = i64, const N: usize = 3> {
+    cuboids: Vec<Cuboid<T, N>>,
+}
+
+impl<T, const N: usize> Default for CuboidSet<T, N> {
+    fn default() -> Self {
+        Self {
+            cuboids: Vec::new(),
+        }
+    }
+}
+
+impl<T: Scalar, const N: usize> CuboidSet<T, N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The disjoint cuboids making up the set.
+    pub fn cuboids(&self) -> &[Cuboid<T, N>] {
+        &self.cuboids
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cuboids.is_empty()
+    }
+
+    pub fn volume(&self) -> T {
+        self.cuboids.iter().fold(T::zero(), |v, c| v + c.volume())
+    }
+
+    pub fn contains(&self, point: Vector<T, N>) -> bool {
+        self.cuboids.iter().any(|c| c.contains(point))
+    }
+
+    /// Adds every point of the cuboid.
+    pub fn insert(&mut self, cuboid: Cuboid<T, N>) {
+        self.remove(cuboid);
+        if !cuboid.is_empty() {
+            self.cuboids.push(cuboid);
+        }
+    }
+
+    /// Removes every point of the cuboid.
+    pub fn remove(&mut self, cuboid: Cuboid<T, N>) {
+        self.cuboids = self
+            .cuboids
+            .iter()
+            .flat_map(|c| c.difference(cuboid))
+            .collect();
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ret = self.clone();
+        for c in other.cuboids.iter() {
+            ret.insert(*c);
+        }
+        ret
+    }
+
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Self {
+        // Pieces of disjoint cuboids are still disjoint.
+        let cuboids = self
+            .cuboids
+            .iter()
+            .flat_map(|c1| other.cuboids.iter().filter_map(|c2| c1.intersect(*c2)))
+            .filter(|c| !c.is_empty())
+            .collect();
+        Self { cuboids }
+    }
+
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ret = self.clone();
+        for c in other.cuboids.iter() {
+            ret.remove(*c);
+        }
+        ret
+    }
+}
+
+impl<T: Scalar, const N: usize> From<Cuboid<T, N>> for CuboidSet<T, N> {
+    fn from(cuboid: Cuboid<T, N>) -> Self {
+        let mut ret = Self::new();
+        ret.insert(cuboid);
+        ret
+    }
+}
+
+impl<T: Scalar, const N: usize> FromIterator<Cuboid<T, N>> for CuboidSet<T, N> {
+    fn from_iter<I: IntoIterator<Item = Cuboid<T, N>>>(iter: I) -> Self {
+        let mut ret = Self::new();
+        for cuboid in iter {
+            ret.insert(cuboid);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test_cuboid_set {
+    use std::collections::HashSet;
+
+    use itertools::Itertools;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn cuboid(lo: [i64; 3], hi: [i64; 3]) -> Cuboid {
+        Cuboid::new(Vector::from(lo), Vector::from(hi)).unwrap()
+    }
+
+    fn random_cuboid(rng: &mut SmallRng) -> Cuboid {
+        let mut lo = [0; 3];
+        let mut hi = [0; 3];
+        for i in 0..3 {
+            let (a, b) = (rng.gen_range(0..8), rng.gen_range(0..8));
+            lo[i] = a.min(b);
+            hi[i] = a.max(b);
+        }
+        cuboid(lo, hi)
+    }
+
+    fn points(set: &CuboidSet) -> HashSet<[i64; 3]> {
+        itertools::iproduct!(0..8, 0..8, 0..8)
+            .map(|(x, y, z)| [x, y, z])
+            .filter(|p| set.contains(Vector::from(*p)))
+            .collect()
+    }
+
+    fn assert_disjoint(set: &CuboidSet) {
+        for (c1, c2) in set.cuboids().iter().tuple_combinations() {
+            assert!(c1.intersect(*c2).map_or(true, Cuboid::is_empty));
+        }
+        assert!(set.cuboids().iter().all(|c| !c.is_empty()));
+        assert_eq!(set.volume() as usize, points(set).len());
+    }
+
+    #[test]
+    fn test_cuboid_set_example() {
+        let mut set = CuboidSet::new();
+        set.insert(cuboid([10, 10, 10], [13, 13, 13]));
+        assert_eq!(set.volume(), 27);
+        set.insert(cuboid([11, 11, 11], [14, 14, 14]));
+        assert_eq!(set.volume(), 27 + 19);
+        set.remove(cuboid([9, 9, 9], [12, 12, 12]));
+        assert_eq!(set.volume(), 27 + 19 - 8);
+        set.insert(cuboid([10, 10, 10], [11, 11, 11]));
+        assert_eq!(set.volume(), 39);
+        assert!(set.contains(Vector::from([10, 10, 10])));
+        assert!(!set.contains(Vector::from([11, 11, 10])));
+        assert!(set.contains(Vector::from([13, 13, 13])));
+        assert!(!set.contains(Vector::from([14, 13, 13])));
+    }
+
+    #[test]
+    fn test_cuboid_set_random() {
+        let mut rng = SmallRng::seed_from_u64(22);
+        for _ in 0..50 {
+            let a = (0..4)
+                .map(|_| random_cuboid(&mut rng))
+                .collect::<CuboidSet>();
+            let b = (0..4)
+                .map(|_| random_cuboid(&mut rng))
+                .collect::<CuboidSet>();
+            let (pa, pb) = (points(&a), points(&b));
+            for set in [&a, &b] {
+                assert_disjoint(set);
+            }
+            let union = a.union(&b);
+            assert_disjoint(&union);
+            assert_eq!(points(&union), &pa | &pb);
+            let intersection = a.intersection(&b);
+            assert_disjoint(&intersection);
+            assert_eq!(points(&intersection), &pa & &pb);
+            let difference = a.difference(&b);
+            assert_disjoint(&difference);
+            assert_eq!(points(&difference), &pa - &pb);
+        }
+    }
 }
@@ -1,53 +1,71 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
 use combine::error::StringStreamError;
 use combine::Parser;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use num_traits::Signed;
 
-use self::cuboid::Cuboid;
-use crate::day_22::vector::Vector;
+use self::cuboid::{Cuboid, CuboidSet, Scalar};
+use self::solver::{InclusionExclusion, ReactorSolver, Splitting};
+use crate::vector::Vector;
 
 pub mod cuboid;
-pub mod vector;
+pub mod solver;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub struct Step {
-    pub region: Cuboid,
+pub struct Step<T = i64, const N: usize = 3> {
+    pub region: Cuboid<T, N>,
     pub value: bool,
 }
 
-impl Step {
-    pub fn volume(&self) -> i64 {
-        (if self.value { 1 } else { -1 }) * self.region.volume()
+impl<T: Scalar + Signed, const N: usize> Step<T, N> {
+    pub fn volume(&self) -> T {
+        if self.value {
+            self.region.volume()
+        } else {
+            -self.region.volume()
+        }
     }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
-pub struct Input {
-    pub steps: Vec<Step>,
+pub struct Input<T = i64, const N: usize = 3> {
+    pub steps: Vec<Step<T, N>>,
 }
 
-impl Input {
-    pub fn solve(self) -> i64 {
-        let mut decomposed_steps = Vec::<Step>::new();
-        for s in self.steps {
-            decomposed_steps.extend(
-                decomposed_steps
-                    .par_iter()
-                    .flat_map(|d| {
-                        let region = s.region.intersect(d.region)?;
-                        let value = !d.value;
-                        Some(Step { region, value })
-                    })
-                    .collect::<Vec<_>>(),
-            );
-            if s.value {
-                decomposed_steps.push(s);
-            }
-        }
-        decomposed_steps.iter().map(Step::volume).sum()
+impl<T: Scalar, const N: usize> Input<T, N> {
+    /// Runs the steps, returning the cubes that end up on.
+    pub fn run(&self) -> CuboidSet<T, N> {
+        Splitting.run(&self.steps)
+    }
+
+    /// Counts the cubes that are on after the steps, by
+    /// inclusion–exclusion.  Use `solve_with` to pick another solver.
+    pub fn solve(self) -> T
+    where
+        T: Signed,
+    {
+        self.solve_with(&InclusionExclusion)
+    }
+
+    pub fn solve_with<S: ReactorSolver<T, N>>(&self, solver: &S) -> T {
+        solver.volume(&self.steps)
     }
 }
 
+/// Parses the puzzle's three-dimensional steps.
 pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
+    parse_steps()
+}
+
+/// Parses steps with `N` ranges each, such as `on x=1..2,y=3..4` for
+/// two dimensions.  The ranges can have any single-letter labels, but
+/// are always taken in order.
+pub fn parse_steps<'a, T, const N: usize>() -> impl Parser<&'a str, Output = Input<T, N>>
+where
+    T: Scalar + Signed + FromStr,
+    T::Err: Display,
+{
     use combine::parser::char::*;
     use combine::*;
 
@@ -60,33 +78,69 @@ pub fn parse_input<'a>() -> impl Parser<&'a str, Output = Input> {
         )
     }
 
-    fn range<'a>(label: char) -> impl Parser<&'a str, Output = (i64, i64)> {
-        char(label)
+    fn range<'a, T>() -> impl Parser<&'a str, Output = (T, T)>
+    where
+        T: Signed + FromStr,
+        T::Err: Display,
+    {
+        letter()
             .skip(char('='))
             .with(combine_parse_integral())
             .skip(string(".."))
             .and(combine_parse_integral())
     }
 
-    fn step<'a>() -> impl Parser<&'a str, Output = Step> {
+    fn step<'a, T, const N: usize>() -> impl Parser<&'a str, Output = Step<T, N>>
+    where
+        T: Scalar + Signed + FromStr,
+        T::Err: Display,
+    {
         bool()
             .skip(char(' '))
-            .and(range('x'))
-            .skip(char(','))
-            .and(range('y'))
-            .skip(char(','))
-            .and(range('z'))
-            .flat_map(|(((value, (x_lo, x_hi)), (y_lo, y_hi)), (z_lo, z_hi))| {
-                let lo = Vector::from([x_lo, y_lo, z_lo]);
-                let hi = Vector::from([x_hi, y_hi, z_hi]).add_scalar(1);
-                let region = Cuboid::new(lo, hi).ok_or(StringStreamError::UnexpectedParse)?;
+            .and(sep_by1::<Vec<_>, _, _, _>(range::<T>(), char(',')))
+            .flat_map(|(value, ranges)| {
+                let (lo, hi): (Vec<T>, Vec<T>) = ranges.into_iter().unzip();
+                let lo = Vector::try_from(lo).map_err(|_| StringStreamError::UnexpectedParse)?;
+                let hi = Vector::try_from(hi).map_err(|_| StringStreamError::UnexpectedParse)?;
+                let region = Cuboid::new(lo, hi.add_scalar(T::one()))
+                    .ok_or(StringStreamError::UnexpectedParse)?;
                 Ok(Step { region, value })
             })
     }
 
-    fn steps<'a>() -> impl Parser<&'a str, Output = Vec<Step>> {
+    fn steps<'a, T, const N: usize>() -> impl Parser<&'a str, Output = Vec<Step<T, N>>>
+    where
+        T: Scalar + Signed + FromStr,
+        T::Err: Display,
+    {
         sep_end_by(step(), newline())
     }
 
     steps().skip(eof()).map(|steps| Input { steps })
 }
+
+#[cfg(test)]
+mod test_parse_steps {
+    use super::*;
+
+    #[test]
+    fn test_parse_steps_dimensions() {
+        let (input, _) = parse_steps::<i64, 2>()
+            .parse("on x=0..9,y=0..9\noff a=-2..2,b=5..5\n")
+            .unwrap();
+        assert_eq!(input.solve(), 100 - 3);
+
+        let (input, _) = parse_steps::<i32, 4>()
+            .parse("on x=1..2,y=1..2,z=1..2,w=1..2\n")
+            .unwrap();
+        assert_eq!(input.steps[0].region.hi(), Vector::from([3; 4]));
+        assert_eq!(input.solve(), 16);
+    }
+
+    #[test]
+    fn test_parse_steps_errors() {
+        assert!(parse_steps::<i64, 3>().parse("on x=0..9,y=0..9\n").is_err());
+        assert!(parse_steps::<i64, 1>().parse("on x=0..9,y=0..9\n").is_err());
+        assert!(parse_steps::<i64, 1>().parse("on x=9..0\n").is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use aoc2021::day_22::cuboid::Cuboid;
-use aoc2021::day_22::vector::Vector;
 use aoc2021::day_22::Input;
+use aoc2021::vector::Vector;
 
 fn run(mut input: Input) -> i64 {
     let lo = Vector::repeat(-50);
@@ -0,0 +1,218 @@
+//! Interchangeable strategies for running reboot steps.
+
+use itertools::Itertools;
+use num_traits::Signed;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use rayon::slice::ParallelSlice;
+
+use super::cuboid::{CuboidSet, Scalar};
+use super::Step;
+
+/// A way of counting the cubes that are on after a list of steps.
+pub trait ReactorSolver<T, const N: usize> {
+    fn volume(&self, steps: &[Step<T, N>]) -> T;
+}
+
+/// Inclusion–exclusion: each step cancels out its overlap with every
+/// earlier term by adding a term of the opposite sign.  The number of
+/// terms can grow quadratically with heavy overlap.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct InclusionExclusion;
+
+impl<T: Scalar + Signed, const N: usize> ReactorSolver<T, N> for InclusionExclusion {
+    fn volume(&self, steps: &[Step<T, N>]) -> T {
+        let mut decomposed_steps = Vec::<Step<T, N>>::new();
+        for s in steps {
+            decomposed_steps.extend(
+                decomposed_steps
+                    .par_iter()
+                    .flat_map(|d| {
+                        let region = s.region.intersect(d.region)?;
+                        let value = !d.value;
+                        Some(Step { region, value })
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            if s.value {
+                decomposed_steps.push(*s);
+            }
+        }
+        decomposed_steps
+            .iter()
+            .fold(T::zero(), |v, s| v + s.volume())
+    }
+}
+
+/// Splitting: keeps the cubes that are on as a `CuboidSet`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Splitting;
+
+impl Splitting {
+    /// Runs the steps, returning the cubes that end up on.
+    pub fn run<T: Scalar, const N: usize>(&self, steps: &[Step<T, N>]) -> CuboidSet<T, N> {
+        let mut set = CuboidSet::new();
+        for s in steps {
+            if s.value {
+                set.insert(s.region);
+            } else {
+                set.remove(s.region);
+            }
+        }
+        set
+    }
+}
+
+impl<T: Scalar, const N: usize> ReactorSolver<T, N> for Splitting {
+    fn volume(&self, steps: &[Step<T, N>]) -> T {
+        self.run(steps).volume()
+    }
+}
+
+/// Coordinate compression: the steps' faces cut space into a grid of
+/// blocks that are each entirely on or off.  The grid is swept one
+/// slab at a time along each axis in turn, keeping only the steps
+/// that cover the slab, so the memory used stays linear in the number
+/// of steps `n`.  Each axis has up to `2n` cuts, and each slab takes
+/// `O(n)` to filter, so the time is `O(n^(N + 1))` in the worst case.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Compression;
+
+/// The sorted, distinct boundaries along an axis.
+fn cuts<T: Scalar, const N: usize>(steps: &[&Step<T, N>], axis: usize) -> Vec<T> {
+    steps
+        .iter()
+        .flat_map(|s| {
+            let (lo, hi) = s.region.extent(axis);
+            [lo, hi]
+        })
+        .sorted()
+        .dedup()
+        .collect()
+}
+
+/// The steps that cover the slab from `lo` to `hi` along an axis.
+fn slab<'a, T: Scalar, const N: usize>(
+    steps: &[&'a Step<T, N>],
+    axis: usize,
+    lo: T,
+    hi: T,
+) -> Vec<&'a Step<T, N>> {
+    steps
+        .iter()
+        .copied()
+        .filter(|s| {
+            let (s_lo, s_hi) = s.region.extent(axis);
+            s_lo <= lo && hi <= s_hi
+        })
+        .collect()
+}
+
+/// The volume that is on within a block of the grid, given the steps
+/// that cover the block along the axes before `axis`.
+fn compressed_volume<T: Scalar, const N: usize>(steps: &[&Step<T, N>], axis: usize) -> T {
+    match steps.last() {
+        None => T::zero(),
+        // Every step covers the whole block, so the last one wins.
+        Some(s) if axis == N => {
+            if s.value {
+                T::one()
+            } else {
+                T::zero()
+            }
+        }
+        Some(_) => cuts(steps, axis)
+            .windows(2)
+            .map(|w| (w[1] - w[0]) * compressed_volume(&slab(steps, axis, w[0], w[1]), axis + 1))
+            .fold(T::zero(), |v, x| v + x),
+    }
+}
+
+impl<T: Scalar, const N: usize> ReactorSolver<T, N> for Compression {
+    fn volume(&self, steps: &[Step<T, N>]) -> T {
+        let steps = steps.iter().collect_vec();
+        if N == 0 {
+            return compressed_volume(&steps, 0);
+        }
+        // Only the outermost sweep is parallel.
+        cuts(&steps, 0)
+            .par_windows(2)
+            .map(|w| (w[1] - w[0]) * compressed_volume(&slab(&steps, 0, w[0], w[1]), 1))
+            .reduce(T::zero, |v, x| v + x)
+    }
+}
+
+#[cfg(test)]
+mod test_solver {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::day_22::cuboid::Cuboid;
+    use crate::vector::Vector;
+
+    fn random_steps<const N: usize>(rng: &mut SmallRng, n: usize, size: i64) -> Vec<Step<i64, N>> {
+        (0..n)
+            .map(|_| {
+                let mut lo = [0; N];
+                let mut hi = [0; N];
+                for i in 0..N {
+                    let (a, b) = (rng.gen_range(-size..=size), rng.gen_range(-size..=size));
+                    lo[i] = a.min(b);
+                    hi[i] = a.max(b) + 1;
+                }
+                Step {
+                    region: Cuboid::new(Vector::from(lo), Vector::from(hi)).unwrap(),
+                    value: rng.gen_bool(0.6),
+                }
+            })
+            .collect()
+    }
+
+    fn brute_force<const N: usize>(steps: &[Step<i64, N>], size: i64) -> i64 {
+        (0..N)
+            .map(|_| -size..=size)
+            .multi_cartesian_product()
+            .filter(|p| {
+                let p = Vector::<i64, N>::try_from(p.clone()).unwrap();
+                steps
+                    .iter()
+                    .rev()
+                    .find(|s| s.region.contains(p))
+                    .map_or(false, |s| s.value)
+            })
+            .count() as i64
+    }
+
+    fn assert_solvers_agree<const N: usize>(steps: &[Step<i64, N>], expected: i64) {
+        assert_eq!(InclusionExclusion.volume(steps), expected);
+        assert_eq!(Splitting.volume(steps), expected);
+        assert_eq!(Compression.volume(steps), expected);
+    }
+
+    #[test]
+    fn test_solvers_agree() {
+        let mut rng = SmallRng::seed_from_u64(47);
+        for n in 0..30 {
+            let steps = random_steps::<3>(&mut rng, n, 6);
+            assert_solvers_agree(&steps, brute_force(&steps, 6));
+        }
+    }
+
+    #[test]
+    fn test_solvers_agree_large() {
+        let mut rng = SmallRng::seed_from_u64(2022);
+        let steps = random_steps::<3>(&mut rng, 40, 100_000);
+        assert_solvers_agree(&steps, Splitting.volume(&steps));
+    }
+
+    #[test]
+    fn test_solvers_other_dimensions() {
+        let mut rng = SmallRng::seed_from_u64(48);
+        for n in 0..15 {
+            let steps = random_steps::<2>(&mut rng, n, 8);
+            assert_solvers_agree(&steps, brute_force(&steps, 8));
+            let steps = random_steps::<4>(&mut rng, n, 3);
+            assert_solvers_agree(&steps, brute_force(&steps, 3));
+        }
+    }
+}
@@ -30,9 +30,10 @@ mod room;
 
 use itertools::Itertools;
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashSet};
+use std::collections::{BinaryHeap, HashMap};
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::rc::Rc;
 
 use combine::stream::ResetStream;
 use combine::{Parser, Positioned};
@@ -61,23 +62,40 @@ impl<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize>
         }
     }
 
-    pub fn solve(self, params: &Params) -> u32 {
+    /// Finds a cheapest sequence of moves that solves this state.
+    pub fn solve(self, params: &Params) -> Solution<NUM_ROOMS, ROOM_SIZE, HALL_SIZE> {
         let mut open = BinaryHeap::new();
-        let mut closed = HashSet::new();
+        // Maps each expanded state to the state it was reached from
+        // and the cost of that move.
+        let mut closed = HashMap::new();
         let x = StateBundle::new(self, 0, params);
         open.push(x);
-        loop {
+        let goal = loop {
             let x = open.pop().unwrap();
-            if x.state.is_solved(params) {
-                break x.cost_so_far
+            if closed.contains_key(&x.state) {
+                continue;
             }
-            for future in x.futures(params) {
-                if !closed.contains(&x.state) {
+            let state = Rc::new(x.state);
+            closed.insert(Rc::clone(&state), (x.parent, x.move_cost));
+            if state.is_solved(params) {
+                break state;
+            }
+            for future in StateBundle::futures(&state, x.cost_so_far, x.total_cost_est, params) {
+                if !closed.contains_key(&future.state) {
                     open.push(future);
                 }
             }
-            closed.insert(x.state);
+        };
+
+        let mut steps = Vec::new();
+        let mut state = Some(goal);
+        while let Some(s) = state {
+            let (parent, move_cost) = &closed[&s];
+            steps.push(((*s).clone(), *move_cost));
+            state = parent.clone();
         }
+        steps.reverse();
+        Solution { steps }
     }
 
     fn is_solved(&self, params: &Params) -> bool {
@@ -308,16 +326,20 @@ impl<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize>
     }
 }
 
-/// A state bundled together with its exact cost so far and its
-/// estimated total cost.  States are ordered first by their total
-/// cost estimates, and then by their costs so far (in reverse order,
-/// since Rust's `BinaryHeap` is a max-heap, and we're trying to
-/// minimize the cost).
+/// A state bundled together with its exact cost so far, its
+/// estimated total cost, and how it was reached.  States are ordered
+/// first by their total cost estimates, and then by their costs so far
+/// (in reverse order, since Rust's `BinaryHeap` is a max-heap, and
+/// we're trying to minimize the cost).
 #[derive(PartialEq, Eq, Clone, Hash, Debug)]
 struct StateBundle<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize> {
     state: State<NUM_ROOMS, ROOM_SIZE, HALL_SIZE>,
     cost_so_far: u32,
     total_cost_est: u32,
+    /// The expanded state this one was reached from.
+    parent: Option<Rc<State<NUM_ROOMS, ROOM_SIZE, HALL_SIZE>>>,
+    /// The cost of the move from the parent state.
+    move_cost: u32,
 }
 
 impl<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize> PartialOrd
@@ -351,18 +373,58 @@ impl<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize>
             state,
             cost_so_far,
             total_cost_est: cost_so_far + est,
+            parent: None,
+            move_cost: 0,
         }
     }
 
-    /// List of state bundles one move in the future from this state.
-    fn futures<'a>(&'a self, params: &'a Params) -> impl Iterator<Item = Self> + 'a {
-        self.state
+    /// List of state bundles one move in the future from an expanded
+    /// state with the given cost so far and estimated total cost.
+    fn futures<'a>(
+        state: &'a Rc<State<NUM_ROOMS, ROOM_SIZE, HALL_SIZE>>,
+        cost_so_far: u32,
+        total_cost_est: u32,
+        params: &'a Params,
+    ) -> impl Iterator<Item = Self> + 'a {
+        state
             .futures(params)
-            .map(|(state, cost, est_cost_change)| Self {
-                state,
-                cost_so_far: self.cost_so_far + cost,
-                total_cost_est: self.total_cost_est + est_cost_change,
+            .map(move |(future, cost, est_cost_change)| Self {
+                state: future,
+                cost_so_far: cost_so_far + cost,
+                total_cost_est: total_cost_est + est_cost_change,
+                parent: Some(Rc::clone(state)),
+                move_cost: cost,
+            })
+    }
+}
+
+/// A cheapest solution, as the sequence of states from the initial
+/// state to the solved state.  Each state is paired with the cost of
+/// the move that reached it, which is 0 for the initial state.
+#[derive(Clone, Debug)]
+pub struct Solution<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize> {
+    pub steps: Vec<(State<NUM_ROOMS, ROOM_SIZE, HALL_SIZE>, u32)>,
+}
+
+impl<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize>
+    Solution<NUM_ROOMS, ROOM_SIZE, HALL_SIZE>
+{
+    /// The total cost of all the moves.
+    pub fn cost(&self) -> u32 {
+        self.steps.iter().map(|&(_, cost)| cost).sum()
+    }
+
+    /// Pretty-print every state along the solution, each headed by
+    /// the cost of the move into it and the running total.
+    pub fn pp(&self, params: &Params) -> String {
+        let mut total = 0;
+        self.steps
+            .iter()
+            .map(|(state, cost)| {
+                total += cost;
+                format!("cost {} (total {})\n{}\n", cost, total, state.pp(params))
             })
+            .join("\n")
     }
 }
 
@@ -443,3 +505,68 @@ where
                 .collect_vec()
         })
 }
+
+#[cfg(test)]
+mod test_solve {
+    use combine::EasyParser;
+
+    use super::*;
+
+    const EXAMPLE: &str = "\
+#############
+#...........#
+###B#C#B#D###
+  #A#D#C#A#
+  #########
+";
+
+    fn check_path<const NUM_ROOMS: usize, const ROOM_SIZE: usize, const HALL_SIZE: usize>(
+        problem: Problem<NUM_ROOMS, ROOM_SIZE, HALL_SIZE>,
+        expected: u32,
+    ) {
+        let params = &problem.params;
+        let solution = problem.init.clone().solve(params);
+        assert_eq!(solution.cost(), expected);
+        assert_eq!(solution.steps[0], (problem.init, 0));
+        assert!(solution.steps.last().unwrap().0.is_solved(params));
+        for ((prev, _), (next, cost)) in solution.steps.iter().tuple_windows() {
+            assert!(prev
+                .futures(params)
+                .any(|(state, c, _)| state == *next && c == *cost));
+        }
+        let diagrams = solution.pp(params);
+        assert_eq!(diagrams.matches("cost ").count(), solution.steps.len());
+        assert!(diagrams.ends_with(&format!(
+            "(total {})\n{}\n",
+            expected,
+            solution.steps.last().unwrap().0.pp(params)
+        )));
+    }
+
+    #[test]
+    fn test_solve_example() {
+        let (cols, _) = parse_cols().easy_parse(EXAMPLE).unwrap();
+        check_path(Problem::<4, 2, 11>::from_cols(&cols), 12521);
+    }
+
+    #[test]
+    fn test_solve_example_unfolded() {
+        let (cols, _) = parse_cols().easy_parse(EXAMPLE).unwrap();
+        let extra = [[3, 3], [1, 2], [0, 1], [2, 0]];
+        let cols = cols
+            .iter()
+            .zip(extra)
+            .map(|(col, [a, b])| vec![col[0], a, b, col[1]])
+            .collect_vec();
+        check_path(Problem::<4, 4, 11>::from_cols(&cols), 44169);
+    }
+
+    #[test]
+    fn test_solve_solved() {
+        let cols = (0..4).map(|i| vec![i, i]).collect_vec();
+        let problem = Problem::<4, 2, 11>::from_cols(&cols);
+        let solution = problem.init.clone().solve(&problem.params);
+        assert_eq!(solution.steps, vec![(problem.init, 0)]);
+        assert_eq!(solution.cost(), 0);
+    }
+}
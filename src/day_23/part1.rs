@@ -2,7 +2,11 @@ use aoc2021::day_23::Problem;
 
 fn run(cols: Vec<Vec<u32>>) -> u32 {
     let problem = Problem::<4, 2, 11>::from_cols(&cols);
-    problem.init.solve(&problem.params)
+    let solution = problem.init.solve(&problem.params);
+    if aoc2021::flag("diagrams") {
+        println!("{}", solution.pp(&problem.params));
+    }
+    solution.cost()
 }
 
 aoc2021::make_main_combine_easy!(23, aoc2021::day_23::parse_cols, run);
@@ -7,7 +7,11 @@ fn run(cols: Vec<Vec<u32>>) -> u32 {
     let col3 = vec![cols[3][0], 2, 0, cols[3][1]];
     let cols = vec![col0, col1, col2, col3];
     let problem = Problem::<4, 4, 11>::from_cols(&cols);
-    problem.init.solve(&problem.params)
+    let solution = problem.init.solve(&problem.params);
+    if aoc2021::flag("diagrams") {
+        println!("{}", solution.pp(&problem.params));
+    }
+    solution.cost()
 }
 
 aoc2021::make_main_combine_easy!(23, aoc2021::day_23::parse_cols, run);
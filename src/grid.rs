@@ -0,0 +1,229 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// An index into a grid, in `(row, column)` order.
+pub type Ix = (usize, usize);
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+struct Frontier {
+    ix: Ix,
+    cost: u32,
+    est: u32,
+}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap, and we always want to find the
+        // least expensive path, so we reverse the ordering.
+        other.est.cmp(&self.est)
+    }
+}
+
+/// The Manhattan distance between two indices.
+pub fn manhattan(ix1: Ix, ix2: Ix) -> u32 {
+    (ix1.0.abs_diff(ix2.0) + ix1.1.abs_diff(ix2.1)) as u32
+}
+
+/// The indices directly above, below, left and right of `ix` that lie
+/// within a grid of shape `(h, w)`.
+pub fn cardinal_neighbors((h, w): (usize, usize), (i, j): Ix) -> Vec<Ix> {
+    [
+        i.checked_sub(1).map(|i| (i, j)),
+        i.checked_add(1).filter(|&i| i < h).map(|i| (i, j)),
+        j.checked_sub(1).map(|j| (i, j)),
+        j.checked_add(1).filter(|&j| j < w).map(|j| (i, j)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// The Chebyshev (king's move) distance between two indices.
+pub fn chebyshev(ix1: Ix, ix2: Ix) -> u32 {
+    ix1.0.abs_diff(ix2.0).max(ix1.1.abs_diff(ix2.1)) as u32
+}
+
+/// A heuristic for `shortest_path` giving the Manhattan distance to
+/// the nearest goal.
+///
+/// This is admissible for cardinal neighbors, as long as entering a
+/// cell always costs at least 1.
+pub fn manhattan_to(goals: &[Ix]) -> impl Fn(Ix) -> u32 + '_ {
+    move |ix| goals.iter().map(|&g| manhattan(ix, g)).min().unwrap_or(0)
+}
+
+/// A heuristic for `shortest_path` giving the Chebyshev distance to
+/// the nearest goal.
+///
+/// This is admissible for cardinal and diagonal neighbors, as long as
+/// entering a cell always costs at least 1.
+pub fn chebyshev_to(goals: &[Ix]) -> impl Fn(Ix) -> u32 + '_ {
+    move |ix| goals.iter().map(|&g| chebyshev(ix, g)).min().unwrap_or(0)
+}
+
+/// Finds a least expensive path from any of `starts` to any of
+/// `goals` using A*.
+///
+/// `neighbors` lists the cells reachable in one move from a cell
+/// (e.g., `Array2Ext::cardinal_neighbor_indices`), and `cost` gives
+/// the cost of entering a cell.  Start cells are free.  `heuristic`
+/// must never overestimate the remaining cost (see `manhattan_to` and
+/// `chebyshev_to`); `|_| 0` turns this into Dijkstra's algorithm.
+///
+/// Returns the total cost together with the path, including both
+/// endpoints, or `None` if no goal is reachable.
+pub fn shortest_path<S, G, N, NI, C, H>(
+    starts: S,
+    goals: G,
+    mut neighbors: N,
+    mut cost: C,
+    heuristic: H,
+) -> Option<(u32, Vec<Ix>)>
+where
+    S: IntoIterator<Item = Ix>,
+    G: IntoIterator<Item = Ix>,
+    N: FnMut(Ix) -> NI,
+    NI: IntoIterator<Item = Ix>,
+    C: FnMut(Ix) -> u32,
+    H: Fn(Ix) -> u32,
+{
+    let goals = goals.into_iter().collect::<HashSet<_>>();
+    let mut heap = BinaryHeap::new();
+    let mut costs = HashMap::new();
+    let mut parents = HashMap::new();
+    for ix in starts {
+        costs.insert(ix, 0);
+        heap.push(Frontier {
+            ix,
+            cost: 0,
+            est: heuristic(ix),
+        });
+    }
+    while let Some(next) = heap.pop() {
+        if goals.contains(&next.ix) {
+            let mut path = vec![next.ix];
+            while let Some(&ix) = parents.get(path.last().unwrap()) {
+                path.push(ix);
+            }
+            path.reverse();
+            return Some((next.cost, path));
+        }
+        if next.cost > costs[&next.ix] {
+            continue;
+        }
+        for ix in neighbors(next.ix) {
+            let c = next.cost + cost(ix);
+            if costs.get(&ix).map_or(true, |&old| c < old) {
+                costs.insert(ix, c);
+                parents.insert(ix, next.ix);
+                heap.push(Frontier {
+                    ix,
+                    cost: c,
+                    est: c + heuristic(ix),
+                });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test_shortest_path {
+    use ndarray::{array, Array2};
+
+    use super::*;
+    use crate::Array2Ext;
+
+    fn cardinal(a: &Array2<u32>, starts: &[Ix], goals: &[Ix]) -> Option<(u32, Vec<Ix>)> {
+        shortest_path(
+            starts.iter().copied(),
+            goals.iter().copied(),
+            |ix| {
+                a.cardinal_neighbor_indices(ix)
+                    .into_iter()
+                    .filter(|&ix| a[ix] != 0)
+            },
+            |ix| a[ix],
+            manhattan_to(goals),
+        )
+    }
+
+    #[test]
+    fn test_shortest_path_detour() {
+        let a = array![[1, 9, 1], [1, 9, 1], [1, 1, 1]];
+        assert_eq!(
+            cardinal(&a, &[(0, 0)], &[(0, 2)]),
+            Some((
+                6,
+                vec![(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (1, 2), (0, 2)]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_unreachable() {
+        // Zero marks a wall.
+        let a = array![[1, 0, 1], [1, 0, 1], [1, 0, 1]];
+        assert_eq!(cardinal(&a, &[(0, 0)], &[(2, 2)]), None);
+    }
+
+    #[test]
+    fn test_shortest_path_multiple_starts_and_goals() {
+        let a = array![[1, 5, 5, 1], [5, 5, 5, 4], [1, 5, 5, 1]];
+        assert_eq!(
+            cardinal(&a, &[(0, 0), (2, 3)], &[(0, 3), (2, 0)]),
+            Some((5, vec![(2, 3), (1, 3), (0, 3)]))
+        );
+        assert_eq!(cardinal(&a, &[(1, 1)], &[(1, 1)]), Some((0, vec![(1, 1)])));
+    }
+
+    #[test]
+    fn test_shortest_path_zero_cost() {
+        // Manhattan distance would overestimate here and return the
+        // direct path along the top, which costs 3.
+        let a = array![[1, 1, 1, 1], [0, 9, 9, 0], [0, 0, 0, 0]];
+        assert_eq!(
+            shortest_path(
+                [(0, 0)],
+                [(0, 3)],
+                |ix| a.cardinal_neighbor_indices(ix),
+                |ix| a[ix],
+                |_| 0,
+            )
+            .map(|(cost, _)| cost),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_shortest_path_diagonal() {
+        let a = Array2::from_elem((4, 4), 1);
+        let goals = [(3, 3)];
+        let (cost, path) = shortest_path(
+            [(0, 0)],
+            goals,
+            |ix| a.neighbor_indices(ix),
+            |ix| a[ix],
+            chebyshev_to(&goals),
+        )
+        .unwrap();
+        assert_eq!(cost, 3);
+        assert_eq!(path, vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn test_cardinal_neighbors() {
+        assert_eq!(cardinal_neighbors((3, 2), (0, 0)), vec![(1, 0), (0, 1)]);
+        assert_eq!(
+            cardinal_neighbors((3, 2), (1, 1)),
+            vec![(0, 1), (2, 1), (1, 0)]
+        );
+        assert_eq!(cardinal_neighbors((1, 1), (0, 0)), vec![]);
+    }
+}
@@ -30,6 +30,9 @@ pub mod day_20;
 pub mod day_21;
 pub mod day_22;
 pub mod day_23;
+pub mod grid;
+pub mod vector;
+pub mod zipper;
 
 use std::fmt::Display;
 use std::io::Read;
@@ -49,8 +52,19 @@ use nom::sequence::terminated;
 use nom::IResult;
 use num_traits::{Signed, Num};
 
+/// Whether the option `--name` was passed on the command line.
+pub fn flag(name: &str) -> bool {
+    std::env::args().any(|arg| arg.strip_prefix("--") == Some(name))
+}
+
+/// Reads the puzzle input for day `n`, from the file named on the
+/// command line, from stdin if that name is `-`, or else from the
+/// default input file.  Options starting with `--` are ignored here;
+/// see [`flag`].
 pub fn read_input(n: u32) -> String {
-    let args = std::env::args().collect::<Vec<_>>();
+    let args = std::env::args()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
     let file_name = match args[..] {
         [_, ref input_file] => input_file.clone(),
         [_] => format!("./inputs/{:>02}.txt", n),
@@ -348,10 +362,7 @@ impl<T> Array2Ext for Array2<T> {
     }
 
     fn cardinal_neighbor_indices(&self, ix: (usize, usize)) -> Vec<(usize, usize)> {
-        vec![self.uix(ix), self.dix(ix), self.lix(ix), self.rix(ix)]
-            .into_iter()
-            .flatten()
-            .collect_vec()
+        grid::cardinal_neighbors(self.shape2(), ix)
     }
 
     fn neighbor_indices(&self, ix: (usize, usize)) -> Vec<(usize, usize)> {
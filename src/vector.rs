@@ -0,0 +1,363 @@
+//! Fixed-size vectors with componentwise arithmetic.
+//!
+//! A `Vector<T, N>` is a thin wrapper around `[T; N]`, so every
+//! operation works in place or builds a new array directly, without
+//! going through a heap allocation.
+
+use std::array;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use num_traits::Signed;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Vector<T, const N: usize>([T; N]);
+
+impl<T, const N: usize> Vector<T, N> {
+    pub fn repeat(elem: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::from_fn(|_| elem.clone())
+    }
+
+    /// The vector whose `i`th component is `f(i)`.
+    pub fn from_fn<F>(f: F) -> Self
+    where
+        F: FnMut(usize) -> T,
+    {
+        Vector(array::from_fn(f))
+    }
+
+    /// Collects the items of `iter` into a vector, or returns `None`
+    /// if there are not exactly `N` of them.
+    pub fn try_from_iter<I>(iter: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut iter = iter.into_iter();
+        let items: [Option<T>; N] = array::from_fn(|_| iter.next());
+        if iter.next().is_some() || items.iter().any(Option::is_none) {
+            return None;
+        }
+        Some(Vector(items.map(|t| t.unwrap_or_else(|| unreachable!()))))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.0.iter_mut()
+    }
+
+    pub fn as_array(&self) -> &[T; N] {
+        &self.0
+    }
+
+    pub fn into_array(self) -> [T; N] {
+        self.0
+    }
+
+    pub fn map<U, F>(&self, mut f: F) -> Vector<U, N>
+    where
+        F: FnMut(&T) -> U,
+    {
+        Vector::from_fn(|i| f(&self.0[i]))
+    }
+
+    pub fn map_into<U, F>(self, f: F) -> Vector<U, N>
+    where
+        F: FnMut(T) -> U,
+    {
+        Vector(self.0.map(f))
+    }
+
+    pub fn zip_with<U, V, F>(&self, other: &Vector<U, N>, f: F) -> Vector<V, N>
+    where
+        F: Fn(&T, &U) -> V,
+    {
+        Vector::from_fn(|i| f(&self.0[i], &other.0[i]))
+    }
+
+    pub fn zip_with_into<U, V, F>(self, other: Vector<U, N>, f: F) -> Vector<V, N>
+    where
+        F: Fn(T, U) -> V,
+    {
+        let mut other = other.into_iter();
+        // Both iterators have exactly `N` items, so this never fails.
+        self.map_into(|t| f(t, other.next().unwrap_or_else(|| unreachable!())))
+    }
+
+    pub fn add_scalar<U>(self, rhs: U) -> Vector<T::Output, N>
+    where
+        T: Add<U>,
+        U: Clone,
+    {
+        self.map_into(|t| t + rhs.clone())
+    }
+
+    pub fn sub_scalar<U>(self, rhs: U) -> Vector<T::Output, N>
+    where
+        T: Sub<U>,
+        U: Clone,
+    {
+        self.map_into(|t| t - rhs.clone())
+    }
+
+    #[must_use]
+    pub fn inf(&self, other: &Self) -> Self
+    where
+        T: Clone + Ord,
+    {
+        self.zip_with(other, |t1, t2| t1.min(t2).clone())
+    }
+
+    #[must_use]
+    pub fn sup(&self, other: &Self) -> Self
+    where
+        T: Clone + Ord,
+    {
+        self.zip_with(other, |t1, t2| t1.max(t2).clone())
+    }
+
+    pub fn dot(&self, other: &Self) -> T
+    where
+        T: Copy + Default + Add<Output = T> + Mul<Output = T>,
+    {
+        self.iter()
+            .zip(other)
+            .fold(T::default(), |acc, (&t1, &t2)| acc + t1 * t2)
+    }
+
+    /// The sum of the absolute values of the components, i.e. the
+    /// Manhattan distance from the origin.
+    pub fn norm_l1(&self) -> T
+    where
+        T: Copy + Default + Signed,
+    {
+        self.iter().fold(T::default(), |acc, t| acc + t.abs())
+    }
+
+    /// The square of the Euclidean length.
+    pub fn norm_l2_squared(&self) -> T
+    where
+        T: Copy + Default + Add<Output = T> + Mul<Output = T>,
+    {
+        self.dot(self)
+    }
+
+    /// The largest absolute value of any component, i.e. the
+    /// Chebyshev distance from the origin.
+    pub fn norm_max(&self) -> T
+    where
+        T: Copy + Default + Ord + Signed,
+    {
+        self.iter().map(|t| t.abs()).fold(T::default(), T::max)
+    }
+}
+
+impl<T: Default, const N: usize> Default for Vector<T, N> {
+    fn default() -> Self {
+        Self::from_fn(|_| T::default())
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for Vector<T, N> {
+    fn from(orig: [T; N]) -> Self {
+        Vector(orig)
+    }
+}
+
+impl<T, const N: usize> From<Vector<T, N>> for [T; N] {
+    fn from(orig: Vector<T, N>) -> Self {
+        orig.0
+    }
+}
+
+impl<T, const N: usize> TryFrom<Vec<T>> for Vector<T, N> {
+    type Error = Vec<T>;
+    fn try_from(orig: Vec<T>) -> Result<Self, Self::Error> {
+        <[T; N]>::try_from(orig).map(Vector::from)
+    }
+}
+
+/// Panics unless the iterator yields exactly `N` items; see
+/// `Vector::try_from_iter` for a version that doesn't.
+impl<T, const N: usize> FromIterator<T> for Vector<T, N> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::try_from_iter(iter).expect("wrong number of items for a vector")
+    }
+}
+
+impl<T, const N: usize> Index<usize> for Vector<T, N> {
+    type Output = T;
+    fn index(&self, index: usize) -> &T {
+        &self.0[index]
+    }
+}
+
+impl<T, const N: usize> IndexMut<usize> for Vector<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.0[index]
+    }
+}
+
+impl<T, const N: usize> IntoIterator for Vector<T, N> {
+    type Item = T;
+    type IntoIter = <[T; N] as IntoIterator>::IntoIter;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a Vector<T, N> {
+    type Item = &'a T;
+    type IntoIter = <&'a [T] as IntoIterator>::IntoIter;
+    fn into_iter(self) -> Self::IntoIter {
+        (&self.0).iter()
+    }
+}
+
+impl<T, U, const N: usize> Add<Vector<U, N>> for Vector<T, N>
+where
+    T: Add<U>,
+{
+    type Output = Vector<T::Output, N>;
+    fn add(self, rhs: Vector<U, N>) -> Self::Output {
+        self.zip_with_into(rhs, |t, u| t + u)
+    }
+}
+
+impl<T, U, const N: usize> Sub<Vector<U, N>> for Vector<T, N>
+where
+    T: Sub<U>,
+{
+    type Output = Vector<T::Output, N>;
+    fn sub(self, rhs: Vector<U, N>) -> Self::Output {
+        self.zip_with_into(rhs, |t, u| t - u)
+    }
+}
+
+impl<T, U, const N: usize> AddAssign<Vector<U, N>> for Vector<T, N>
+where
+    T: AddAssign<U>,
+{
+    fn add_assign(&mut self, rhs: Vector<U, N>) {
+        for (t, u) in self.0.iter_mut().zip(rhs) {
+            *t += u;
+        }
+    }
+}
+
+impl<T, U, const N: usize> SubAssign<Vector<U, N>> for Vector<T, N>
+where
+    T: SubAssign<U>,
+{
+    fn sub_assign(&mut self, rhs: Vector<U, N>) {
+        for (t, u) in self.0.iter_mut().zip(rhs) {
+            *t -= u;
+        }
+    }
+}
+
+/// Scales every component by the same factor.
+impl<T, const N: usize> Mul<T> for Vector<T, N>
+where
+    T: Mul + Clone,
+{
+    type Output = Vector<T::Output, N>;
+    fn mul(self, rhs: T) -> Self::Output {
+        self.map_into(|t| t * rhs.clone())
+    }
+}
+
+impl<T, const N: usize> MulAssign<T> for Vector<T, N>
+where
+    T: MulAssign + Clone,
+{
+    fn mul_assign(&mut self, rhs: T) {
+        for t in self.iter_mut() {
+            *t *= rhs.clone();
+        }
+    }
+}
+
+impl<T, const N: usize> Neg for Vector<T, N>
+where
+    T: Neg,
+{
+    type Output = Vector<T::Output, N>;
+    fn neg(self) -> Self::Output {
+        self.map_into(|t| -t)
+    }
+}
+
+impl<T, const N: usize> Sum for Vector<T, N>
+where
+    T: Default + Add<Output = T>,
+{
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, v| acc + v)
+    }
+}
+
+#[cfg(test)]
+mod test_vector {
+    use super::*;
+
+    #[test]
+    fn test_vector_ops() {
+        let u = Vector::from([1, -2, 3]);
+        let v = Vector::from([4, 5, -6]);
+        assert_eq!(u + v, Vector::from([5, 3, -3]));
+        assert_eq!(u - v, Vector::from([-3, -7, 9]));
+        assert_eq!(-u, Vector::from([-1, 2, -3]));
+        assert_eq!(u * 2, Vector::from([2, -4, 6]));
+        assert_eq!(u.add_scalar(1), Vector::from([2, -1, 4]));
+        assert_eq!(u.inf(&v), Vector::from([1, -2, -6]));
+        assert_eq!(u.sup(&v), Vector::from([4, 5, 3]));
+
+        let mut w = u;
+        w += v;
+        w -= Vector::from([1, 1, 1]);
+        w *= 3;
+        w[0] = 0;
+        assert_eq!(w, Vector::from([0, 6, -12]));
+    }
+
+    #[test]
+    fn test_vector_norms() {
+        let u = Vector::from([1, -2, 3]);
+        let v = Vector::from([4, 5, -6]);
+        assert_eq!(u.dot(&v), 4 - 10 - 18);
+        assert_eq!(u.norm_l1(), 6);
+        assert_eq!(u.norm_l2_squared(), 14);
+        assert_eq!(v.norm_max(), 6);
+        assert_eq!(Vector::<i32, 0>::default().norm_max(), 0);
+    }
+
+    #[test]
+    fn test_vector_collect() {
+        let u = Vector::<_, 4>::try_from_iter(1..=4).unwrap();
+        assert_eq!(u, Vector::from([1, 2, 3, 4]));
+        assert_eq!(u.map(|t| t * t).into_array(), [1, 4, 9, 16]);
+        assert_eq!(
+            vec![u, u, Vector::repeat(1)]
+                .into_iter()
+                .sum::<Vector<_, 4>>(),
+            Vector::from([3, 5, 7, 9])
+        );
+        assert_eq!(Vector::<usize, 3>::from_fn(|i| i), Vector::from([0, 1, 2]));
+        assert_eq!(Vector::<_, 4>::try_from_iter(1..=3), None);
+        assert_eq!(Vector::<_, 2>::try_from_iter(1..=3), None);
+        assert_eq!(Vector::<i32, 0>::try_from_iter([]), Some(Vector::from([])));
+        assert_eq!((1..=2).collect::<Vector<_, 2>>(), Vector::from([1, 2]));
+    }
+
+    #[test]
+    #[should_panic(expected = "wrong number of items")]
+    fn test_vector_collect_too_few() {
+        let _ = (1..=3).collect::<Vector<_, 4>>();
+    }
+}
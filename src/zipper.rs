@@ -0,0 +1,466 @@
+//! A zipper over binary trees whose leaves carry arbitrary payloads.
+//!
+//! The zipper keeps the focused subtree alongside the path back to
+//! the top, so moving around and editing near the focus is cheap.
+
+use Direction::*;
+use Node::*;
+
+pub type Tree<T> = Box<Node<T>>;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Node<T> {
+    Leaf(T),
+    Branch(Tree<T>, Tree<T>),
+}
+
+impl<T> Node<T> {
+    pub fn is_leaf(&self) -> bool {
+        matches!(self, Leaf(_))
+    }
+
+    pub fn is_branch(&self) -> bool {
+        matches!(self, Branch(_, _))
+    }
+
+    /// Iterates over the leaves from left to right.
+    pub fn leaves(&self) -> Leaves<'_, T> {
+        Leaves { stack: vec![self] }
+    }
+
+    /// Iterates mutably over the leaves from left to right.
+    pub fn leaves_mut(&mut self) -> LeavesMut<'_, T> {
+        LeavesMut { stack: vec![self] }
+    }
+}
+
+/// An iterator over the leaves of a tree, created by
+/// [`Node::leaves`].
+#[derive(Clone, Debug)]
+pub struct Leaves<'a, T> {
+    stack: Vec<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Leaves<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Leaf(x) => return Some(x),
+                Branch(l, r) => {
+                    self.stack.push(r);
+                    self.stack.push(l);
+                }
+            }
+        }
+    }
+}
+
+/// A mutable iterator over the leaves of a tree, created by
+/// [`Node::leaves_mut`].
+#[derive(Debug)]
+pub struct LeavesMut<'a, T> {
+    stack: Vec<&'a mut Node<T>>,
+}
+
+impl<'a, T> Iterator for LeavesMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.pop()? {
+                Leaf(x) => return Some(x),
+                Branch(l, r) => {
+                    self.stack.push(r);
+                    self.stack.push(l);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Zipper<T> {
+    ctx: Vec<(Direction, Tree<T>)>,
+    focus: Tree<T>,
+}
+
+/// Which child of a branch to take.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+impl<T> Zipper<T> {
+    /// Returns the depth of the current focus.
+    pub fn depth(&self) -> usize {
+        self.ctx.len()
+    }
+
+    /// Returns the path from the top of the tree to the current focus.
+    pub fn path(&self) -> Vec<Direction> {
+        self.ctx.iter().map(|(dir, _)| *dir).collect()
+    }
+
+    /// Indicates whether the top of the tree is currently focused.
+    pub fn at_top(&self) -> bool {
+        self.depth() == 0
+    }
+
+    /// Returns a shared reference the current focus.
+    pub fn focus(&self) -> &Tree<T> {
+        &self.focus
+    }
+
+    /// Returns a mutable reference the current focus.
+    pub fn focus_mut(&mut self) -> &mut Tree<T> {
+        &mut self.focus
+    }
+
+    /// Focuses the immediate left child of the current focus.
+    ///
+    /// Does nothing if the current focus is a leaf.
+    pub fn focus_down_left(&mut self) {
+        crate::take(&mut self.focus, |focus| match focus {
+            box Branch(l, r) => {
+                self.ctx.push((Left, r));
+                l
+            }
+            _ => focus,
+        });
+    }
+
+    /// Focuses the immediate right child of the current focus.
+    ///
+    /// Does nothing if the current focus is a leaf.
+    pub fn focus_down_right(&mut self) {
+        crate::take(&mut self.focus, |focus| match focus {
+            box Branch(l, r) => {
+                self.ctx.push((Right, l));
+                r
+            }
+            _ => focus,
+        });
+    }
+
+    /// Moves the focus up, if possible.
+    ///
+    /// Returns mutable references to the children of the new focus,
+    /// or `None` if the focus is unchanged (i.e., the focus was
+    /// already the top node).
+    pub fn focus_up(&mut self) -> Option<(&mut Tree<T>, &mut Tree<T>)> {
+        self.ctx.pop().map(|p| {
+            crate::take(&mut self.focus, |focus| match p {
+                (Left, right) => box Branch(focus, right),
+                (Right, left) => box Branch(left, focus),
+            });
+            match self.focus {
+                box Branch(ref mut l, ref mut r) => (l, r),
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    /// Focuses the top of the tree.
+    pub fn focus_top(&mut self) {
+        while !self.at_top() {
+            self.focus_up();
+        }
+    }
+
+    /// Focus the next part of the tree according to a depth-first
+    /// traversal.
+    pub fn focus_next_depth_first(&mut self) {
+        if self.focus.is_branch() {
+            self.focus_down_left();
+            return;
+        }
+        loop {
+            match self.ctx.last() {
+                None => break,
+                Some((Left, _)) => {
+                    self.focus_up();
+                    self.focus_down_right();
+                    break;
+                }
+                Some((Right, _)) => {
+                    self.focus_up();
+                }
+            }
+        }
+    }
+
+    /// Focus the next leaf according to a depth-first traversal,
+    /// returning a mutable reference to its contents.
+    pub fn focus_next_leaf_depth_first(&mut self) -> Option<&mut T> {
+        loop {
+            self.focus_next_depth_first();
+            match self.focus {
+                box Leaf(ref mut n) => return Some(n),
+                _ if self.at_top() => return None,
+                _ => (),
+            }
+        }
+    }
+
+    /// Focuses the leftmost child of the current focus, returning a
+    /// mutable reference to its contents.
+    pub fn focus_down_left_leaf(&mut self) -> &mut T {
+        loop {
+            match self.focus {
+                box Leaf(ref mut n) => break n,
+                _ => self.focus_down_left(),
+            }
+        }
+    }
+
+    /// Focuses the rightmost child of the current focus, returning a
+    /// mutable reference to its contents.
+    pub fn focus_down_right_leaf(&mut self) -> &mut T {
+        loop {
+            match self.focus {
+                box Leaf(ref mut n) => break n,
+                _ => self.focus_down_right(),
+            }
+        }
+    }
+
+    /// Focuses the next leaf to the left of the current focus,
+    /// returning a mutable reference to its contents.
+    ///
+    /// If there is no such leaf, focuses the top of the tree and
+    /// returns `None`.
+    pub fn focus_next_leaf_left(&mut self) -> Option<&mut T> {
+        let flag = crate::take_return(&mut self.focus, |mut focus| {
+            let flag = loop {
+                match self.ctx.pop() {
+                    Some((Left, right)) => focus = box Branch(focus, right),
+                    Some((Right, left)) => {
+                        focus = box Branch(left, focus);
+                        break true;
+                    }
+                    None => break false,
+                }
+            };
+            (flag, focus)
+        });
+        if flag {
+            self.focus_down_left();
+            Some(self.focus_down_right_leaf())
+        } else {
+            None
+        }
+    }
+
+    /// Focuses the next leaf to the right of the current focus,
+    /// returning a mutable reference to its contents.
+    ///
+    /// If there is no such leaf, focuses the top of the tree and
+    /// returns `None`.
+    pub fn focus_next_leaf_right(&mut self) -> Option<&mut T> {
+        let flag = crate::take_return(&mut self.focus, |mut focus| {
+            let flag = loop {
+                match self.ctx.pop() {
+                    Some((Right, left)) => focus = box Branch(left, focus),
+                    Some((Left, right)) => {
+                        focus = box Branch(focus, right);
+                        break true;
+                    }
+                    None => break false,
+                }
+            };
+            (flag, focus)
+        });
+        if flag {
+            self.focus_down_right();
+            Some(self.focus_down_left_leaf())
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the next leaf to the left of
+    /// the current focus, or `None` if there is no such leaf.
+    pub fn left_leaf_mut(&mut self) -> Option<&mut T> {
+        let mut it = self.ctx.iter_mut().rev();
+        let mut root = loop {
+            match it.next() {
+                None => return None,
+                Some((Right, left)) => break left,
+                _ => (),
+            }
+        };
+        loop {
+            match root.as_mut() {
+                Leaf(n) => break Some(n),
+                Branch(_, r) => root = r,
+            }
+        }
+    }
+
+    /// Returns a mutable reference to the next leaf to the right of
+    /// the current focus, or `None` if there is no such leaf.
+    pub fn right_leaf_mut(&mut self) -> Option<&mut T> {
+        let mut it = self.ctx.iter_mut().rev();
+        let mut root = loop {
+            match it.next() {
+                None => return None,
+                Some((Left, right)) => break right,
+                _ => (),
+            }
+        };
+        loop {
+            match root.as_mut() {
+                Leaf(n) => break Some(n),
+                Branch(l, _) => root = l,
+            }
+        }
+    }
+
+    /// Iterates over the leaves of the whole tree from left to right,
+    /// without moving the focus.
+    pub fn leaves(&self) -> impl Iterator<Item = &T> {
+        // Left siblings are stored top-down, and right siblings
+        // bottom-up.
+        let left = self.ctx.iter().filter(|(dir, _)| *dir == Right);
+        let right = self.ctx.iter().rev().filter(|(dir, _)| *dir == Left);
+        left.map(|(_, tree)| tree)
+            .chain(Some(&self.focus))
+            .chain(right.map(|(_, tree)| tree))
+            .flat_map(|tree| tree.leaves())
+    }
+
+    /// Iterates mutably over the leaves of the whole tree from left
+    /// to right, without moving the focus.
+    pub fn leaves_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for (dir, tree) in self.ctx.iter_mut() {
+            match dir {
+                Right => left.push(tree),
+                Left => right.push(tree),
+            }
+        }
+        left.into_iter()
+            .chain(Some(&mut self.focus))
+            .chain(right.into_iter().rev())
+            .flat_map(|tree| tree.leaves_mut())
+    }
+}
+
+impl<T> From<Tree<T>> for Zipper<T> {
+    fn from(tree: Tree<T>) -> Self {
+        Zipper {
+            ctx: Vec::new(),
+            focus: tree,
+        }
+    }
+}
+
+impl<T> From<Zipper<T>> for Tree<T> {
+    fn from(zipper: Zipper<T>) -> Self {
+        let mut ret = zipper.focus;
+        for (dir, tree) in zipper.ctx.into_iter().rev() {
+            match dir {
+                Left => ret = box Branch(ret, tree),
+                Right => ret = box Branch(tree, ret),
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test_zipper {
+    use super::*;
+
+    fn leaf<T>(x: T) -> Tree<T> {
+        box Leaf(x)
+    }
+
+    fn branch<T>(l: Tree<T>, r: Tree<T>) -> Tree<T> {
+        box Branch(l, r)
+    }
+
+    /// `[[a,[b,c]],[d,e]]`
+    fn example() -> Tree<char> {
+        branch(
+            branch(leaf('a'), branch(leaf('b'), leaf('c'))),
+            branch(leaf('d'), leaf('e')),
+        )
+    }
+
+    #[test]
+    fn test_leaves() {
+        let mut tree = example();
+        assert_eq!(tree.leaves().collect::<String>(), "abcde");
+        for x in tree.leaves_mut() {
+            *x = x.to_ascii_uppercase();
+        }
+        assert_eq!(tree.leaves().collect::<String>(), "ABCDE");
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut zipper = Zipper::from(example());
+        zipper.focus_down_left();
+        zipper.focus_down_right();
+        assert_eq!(zipper.path(), vec![Left, Right]);
+        assert_eq!(zipper.focus().leaves().collect::<String>(), "bc");
+        assert_eq!(zipper.left_leaf_mut(), Some(&mut 'a'));
+        assert_eq!(zipper.right_leaf_mut(), Some(&mut 'd'));
+        assert_eq!(zipper.leaves().collect::<String>(), "abcde");
+
+        assert_eq!(zipper.focus_down_right_leaf(), &mut 'c');
+        assert_eq!(zipper.depth(), 3);
+        assert_eq!(zipper.focus_next_leaf_right(), Some(&mut 'd'));
+        assert_eq!(zipper.path(), vec![Right, Left]);
+        assert_eq!(zipper.focus_next_leaf_left(), Some(&mut 'c'));
+        assert_eq!(zipper.focus_next_leaf_left(), Some(&mut 'b'));
+        assert_eq!(zipper.focus_next_leaf_left(), Some(&mut 'a'));
+        assert_eq!(zipper.focus_next_leaf_left(), None);
+        assert!(zipper.at_top());
+        assert_eq!(Tree::from(zipper), example());
+    }
+
+    #[test]
+    fn test_depth_first() {
+        let mut zipper = Zipper::from(example());
+        let mut leaves = String::new();
+        while let Some(x) = zipper.focus_next_leaf_depth_first() {
+            leaves.push(*x);
+        }
+        assert_eq!(leaves, "abcde");
+        assert!(zipper.at_top());
+    }
+
+    #[test]
+    fn test_edit() {
+        let mut zipper = Zipper::from(example());
+        zipper.focus_down_right();
+        zipper.focus_down_left();
+        *zipper.focus_mut() = branch(leaf('x'), leaf('y'));
+        for x in zipper.leaves_mut() {
+            *x = x.to_ascii_uppercase();
+        }
+        let (l, r) = zipper.focus_up().unwrap();
+        assert_eq!(l.leaves().collect::<String>(), "XY");
+        assert_eq!(r.leaves().collect::<String>(), "E");
+        zipper.focus_top();
+        assert_eq!(zipper.focus().leaves().collect::<String>(), "ABCXYE");
+        assert!(zipper.focus_up().is_none());
+    }
+
+    #[test]
+    fn test_generic_payload() {
+        let tree = branch(leaf(vec![1, 2]), branch(leaf(vec![]), leaf(vec![3])));
+        let mut zipper = Zipper::from(tree);
+        zipper.focus_down_right_leaf().push(4);
+        assert_eq!(
+            zipper.leaves().flatten().copied().collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+    }
+}